@@ -0,0 +1,185 @@
+use chrono::{DateTime, FixedOffset};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Field added to each output record when context is requested, holding
+/// `"match"` or `"context"`.
+pub const MARKER_FIELD: &str = "match_kind";
+
+/// Which neighbours count as context: any record, or only those sharing a
+/// thread or host with the match.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContextScope {
+    Thread,
+    Hostname,
+}
+
+impl ContextScope {
+    pub fn from_name(name: &str) -> Option<ContextScope> {
+        match name.to_ascii_lowercase().as_str() {
+            "thread" => Some(ContextScope::Thread),
+            "hostname" | "host" => Some(ContextScope::Hostname),
+            _ => None,
+        }
+    }
+
+    pub fn field(self) -> &'static str {
+        match self {
+            ContextScope::Thread => "thread",
+            ContextScope::Hostname => "hostname",
+        }
+    }
+}
+
+/// Why a record is in the output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Selection {
+    Skip,
+    Match,
+    Context,
+}
+
+impl Selection {
+    pub fn marker(self) -> Option<&'static str> {
+        match self {
+            Selection::Skip => None,
+            Selection::Match => Some("match"),
+            Selection::Context => Some("context"),
+        }
+    }
+}
+
+/// Records to keep around each match, like `grep -B/-A`, counted in records
+/// (`before`/`after`) and/or in time either side of the match (`time`).
+#[derive(Debug, Clone, Default)]
+pub struct ContextOptions {
+    pub before: usize,
+    pub after: usize,
+    pub time: Option<Duration>,
+    pub scope: Option<ContextScope>,
+}
+
+impl ContextOptions {
+    pub fn is_active(&self) -> bool {
+        self.before > 0 || self.after > 0 || self.time.is_some()
+    }
+
+    /// Mark every record as a match, context or skipped, given which ones
+    /// matched. Context is the union of the record and time windows.
+    ///
+    /// Neighbours are found in input order, which sf-parser writes sorted
+    /// by timestamp. With a scope, records are only context for matches
+    /// with the same thread/host, and records lacking the field get none.
+    pub fn select(&self, records: &[Value], matched: &[bool]) -> Vec<Selection> {
+        let mut selection: Vec<Selection> = matched.iter()
+            .map(|&m| if m { Selection::Match } else { Selection::Skip })
+            .collect();
+        if !self.is_active() {
+            return selection;
+        }
+
+        let groups: Vec<Vec<usize>> = match self.scope {
+            None => vec![(0..records.len()).collect()],
+            Some(scope) => {
+                let mut groups: HashMap<String, Vec<usize>> = HashMap::new();
+                for (i, record) in records.iter().enumerate() {
+                    match record.get(scope.field()) {
+                        None | Some(Value::Null) => {}
+                        Some(key) => groups.entry(key.to_string()).or_default().push(i),
+                    }
+                }
+                groups.into_values().collect()
+            }
+        };
+
+        let window = self.time.and_then(|t| chrono::Duration::from_std(t).ok());
+        let timestamps: Vec<Option<DateTime<FixedOffset>>> = if window.is_some() {
+            records.iter()
+                .map(|r| r.get("timestamp").and_then(|v| v.as_str()).and_then(|s| DateTime::parse_from_rfc3339(s).ok()))
+                .collect()
+        } else {
+            Vec::new()
+        };
+        let near = |i: usize, match_time: Option<DateTime<FixedOffset>>| match (window, match_time) {
+            (Some(window), Some(match_time)) => timestamps[i].is_some_and(|t| (t - match_time).abs() <= window),
+            _ => false,
+        };
+
+        for members in groups {
+            // Forward for `after`, backward for `before`, each remembering
+            // the nearest match seen so far
+            for (count, forward) in [(self.after, true), (self.before, false)] {
+                let mut remaining = 0;
+                let mut match_time = None;
+                let mut visit = |i: usize| {
+                    if matched[i] {
+                        remaining = count;
+                        match_time = timestamps.get(i).copied().flatten();
+                    } else if remaining > 0 || near(i, match_time) {
+                        remaining = remaining.saturating_sub(1);
+                        selection[i] = Selection::Context;
+                    }
+                };
+                if forward {
+                    members.iter().for_each(|&i| visit(i));
+                } else {
+                    members.iter().rev().for_each(|&i| visit(i));
+                }
+            }
+        }
+        selection
+    }
+
+    /// Human-readable description for the status output.
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if self.before > 0 {
+            parts.push(format!("{} before", self.before));
+        }
+        if self.after > 0 {
+            parts.push(format!("{} after", self.after));
+        }
+        if let Some(time) = self.time {
+            parts.push(format!("±{}s", time.as_secs()));
+        }
+        if let Some(scope) = self.scope {
+            parts.push(format!("same {}", scope.field()));
+        }
+        parts.join(", ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_select() {
+        let records: Vec<Value> = [(0, 1), (10, 2), (20, 1), (30, 2), (40, 1), (100, 2), (110, 2)]
+            .iter()
+            .map(|(secs, thread)| json!({"timestamp": format!("2025-06-12T08:{:02}:{:02}.000000Z", secs / 60, secs % 60), "thread": thread}))
+            .collect();
+        let matched = [false, false, false, true, false, false, false];
+        let kinds = |context: ContextOptions| {
+            context.select(&records, &matched)
+                .iter()
+                .enumerate()
+                .filter_map(|(i, selection)| match selection {
+                    Selection::Skip => None,
+                    Selection::Match => Some(format!("{}m", i)),
+                    Selection::Context => Some(format!("{}c", i)),
+                })
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+
+        assert_eq!(kinds(ContextOptions::default()), "3m");
+        assert_eq!(kinds(ContextOptions { before: 2, after: 1, ..Default::default() }), "1c 2c 3m 4c");
+        assert_eq!(kinds(ContextOptions { before: 2, after: 1, scope: Some(ContextScope::Thread), ..Default::default() }), "1c 3m 5c");
+        assert_eq!(kinds(ContextOptions { time: Some(Duration::from_secs(15)), ..Default::default() }), "2c 3m 4c");
+        assert_eq!(kinds(ContextOptions { time: Some(Duration::from_secs(80)), scope: Some(ContextScope::Thread), ..Default::default() }),
+            "1c 3m 5c 6c");
+    }
+}
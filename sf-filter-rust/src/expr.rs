@@ -0,0 +1,719 @@
+use anyhow::Result;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Range;
+
+use crate::path::FieldPath;
+use crate::predicate::{CompareOp, Literal, Predicate};
+
+/// Byte range of a token in the expression source.
+pub type Span = Range<usize>;
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    Ident(String),
+    Str(String),
+    Number(String),
+    Op(CompareOp),
+    And,
+    Or,
+    Not,
+    In,
+    True,
+    False,
+    Null,
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    Comma,
+    End,
+}
+
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenKind::Ident(name) => write!(f, "`{}`", name),
+            TokenKind::Str(text) => write!(f, "\"{}\"", text),
+            TokenKind::Number(text) => write!(f, "{}", text),
+            TokenKind::Op(op) => write!(f, "`{}`", op.symbol()),
+            TokenKind::And => f.write_str("`and`"),
+            TokenKind::Or => f.write_str("`or`"),
+            TokenKind::Not => f.write_str("`not`"),
+            TokenKind::In => f.write_str("`in`"),
+            TokenKind::True => f.write_str("`true`"),
+            TokenKind::False => f.write_str("`false`"),
+            TokenKind::Null => f.write_str("`null`"),
+            TokenKind::LParen => f.write_str("`(`"),
+            TokenKind::RParen => f.write_str("`)`"),
+            TokenKind::LBracket => f.write_str("`[`"),
+            TokenKind::RBracket => f.write_str("`]`"),
+            TokenKind::Comma => f.write_str("`,`"),
+            TokenKind::End => f.write_str("end of expression"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    span: Span,
+}
+
+/// A comparison in the expression, with where its parts were written so
+/// type errors can point at them.
+#[derive(Debug, Clone)]
+pub struct Comparison {
+    pub predicate: Predicate,
+    field_span: Span,
+    value_spans: Vec<Span>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Node {
+    And(Box<Node>, Box<Node>),
+    Or(Box<Node>, Box<Node>),
+    Not(Box<Node>),
+    Compare(Comparison),
+}
+
+impl Node {
+    fn matches(&self, record: &Value) -> bool {
+        match self {
+            Node::And(a, b) => a.matches(record) && b.matches(record),
+            Node::Or(a, b) => a.matches(record) || b.matches(record),
+            Node::Not(a) => !a.matches(record),
+            Node::Compare(comparison) => comparison.predicate.matches(record),
+        }
+    }
+
+    fn comparisons<'a>(&'a self, out: &mut Vec<&'a Comparison>) {
+        match self {
+            Node::And(a, b) | Node::Or(a, b) => {
+                a.comparisons(out);
+                b.comparisons(out);
+            }
+            Node::Not(a) => a.comparisons(out),
+            Node::Compare(comparison) => out.push(comparison),
+        }
+    }
+}
+
+/// A parsed `--where` expression, e.g.
+///
+/// ```text
+/// (component == "MS" and level == "ERROR") or sf_severity == "Critical"
+/// ```
+///
+/// Comparisons use the operators of `--field` (`==`/`=`, `!=`, `<`, `<=`,
+/// `>`, `>=`, `in [..]`, `not in [..]`, `=~`, `!~`, `contains` with one
+/// value or a list, `like`) or test `exists(field)` / `missing(field)`;
+/// fields may be paths such as `params.volumes[*]`. Conditions combine with
+/// `and`, `or`, `not` (or `&&`, `||`, `!`) and parentheses; `not` binds
+/// tightest, then `and`, then `or`. Text values are quoted. Errors, from
+/// parsing here or from `check` against the input, quote the expression and
+/// underline the offending token.
+#[derive(Debug, Clone)]
+pub struct Expression {
+    source: String,
+    root: Node,
+}
+
+impl Expression {
+    pub fn parse(source: &str) -> Result<Expression> {
+        let tokens = tokenize(source).map_err(|(span, message)| error_at(source, &span, &message))?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let root = parser
+            .parse()
+            .map_err(|(span, message)| error_at(source, &span, &message))?;
+        Ok(Expression {
+            source: source.to_string(),
+            root,
+        })
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn matches(&self, record: &Value) -> bool {
+        self.root.matches(record)
+    }
+
+    /// Type-check the expression against the input records: every field must
+    /// exist in some record, and values must be comparable with what the
+    /// field holds (`serviceID == "230"` never matches a numeric field).
+    /// An empty input has nothing to check against and always passes.
+    pub fn check(&self, records: &[Value]) -> Result<()> {
+        if records.is_empty() {
+            return Ok(());
+        }
+        let mut comparisons = Vec::new();
+        self.root.comparisons(&mut comparisons);
+
+        // One pass collecting the JSON types each referenced field holds
+        let mut types: HashMap<&str, (&FieldPath, FieldTypes)> = comparisons
+            .iter()
+            .map(|c| (c.predicate.field.as_str(), (c.predicate.path(), FieldTypes::default())))
+            .collect();
+        for record in records {
+            for (path, field_types) in types.values_mut() {
+                path.any(record, |value| {
+                    field_types.observe(value);
+                    false
+                });
+            }
+        }
+
+        for comparison in comparisons {
+            let field = comparison.predicate.field.as_str();
+            let field_types = &types[field].1;
+            // Asking whether a field exists is fine whatever the answer
+            if comparison.predicate.op.is_existence() {
+                continue;
+            }
+            if !field_types.present {
+                let mut message = format!("Unknown field `{}`", field);
+                if let Some(suggestion) = closest_field(field, &records[0]) {
+                    message.push_str(&format!(" (did you mean `{}`?)", suggestion));
+                }
+                return Err(error_at(&self.source, &comparison.field_span, &message));
+            }
+            if comparison.predicate.op.is_text() && !field_types.accepts_text(comparison.predicate.op) {
+                let message = format!(
+                    "`{}` holds {}, but `{}` only matches text",
+                    field,
+                    field_types.describe(),
+                    comparison.predicate.op.symbol(),
+                );
+                return Err(error_at(&self.source, &comparison.field_span, &message));
+            }
+            for (literal, span) in comparison.predicate.values.iter().zip(&comparison.value_spans) {
+                if !field_types.accepts(literal) {
+                    let message = format!(
+                        "`{}` holds {}, so {} ({}) never matches",
+                        field,
+                        field_types.describe(),
+                        &self.source[span.clone()],
+                        literal.type_name(),
+                    );
+                    return Err(error_at(&self.source, span, &message));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+/// Which JSON types a field was seen holding (nulls aside).
+#[derive(Debug, Default)]
+struct FieldTypes {
+    present: bool,
+    boolean: bool,
+    number: bool,
+    string: bool,
+    array: bool,
+    other: bool,
+}
+
+impl FieldTypes {
+    fn observe(&mut self, value: &Value) {
+        self.present = true;
+        match value {
+            Value::Null => {}
+            Value::Bool(_) => self.boolean = true,
+            Value::Number(_) => self.number = true,
+            Value::String(_) => self.string = true,
+            Value::Array(_) => self.array = true,
+            Value::Object(_) => self.other = true,
+        }
+    }
+
+    /// Whether `literal` can match some value of this field. Text fields
+    /// compare against the literal as written, so they accept anything.
+    fn accepts(&self, literal: &Literal) -> bool {
+        if self.only_nulls() || self.string || self.array || self.other {
+            return true;
+        }
+        match literal.type_name() {
+            "null" => true,
+            "boolean" => self.boolean,
+            "number" => self.number,
+            _ => false,
+        }
+    }
+
+    fn only_nulls(&self) -> bool {
+        !(self.boolean || self.number || self.string || self.array || self.other)
+    }
+
+    /// Whether a text operator can match: the field holds strings, or
+    /// only nulls so far. `contains` also matches arrays.
+    fn accepts_text(&self, op: CompareOp) -> bool {
+        self.string || self.only_nulls() || (op == CompareOp::Contains && self.array)
+    }
+
+    fn describe(&self) -> String {
+        let kinds: Vec<&str> = [
+            (self.number, "numbers"),
+            (self.boolean, "booleans"),
+            (self.array, "arrays"),
+            (self.other, "objects"),
+        ]
+        .into_iter()
+        .filter_map(|(seen, kind)| seen.then_some(kind))
+        .collect();
+        kinds.join(" and ")
+    }
+}
+
+/// Format `message` followed by the expression with `span` underlined.
+fn error_at(source: &str, span: &Span, message: &str) -> anyhow::Error {
+    let column = source[..span.start].chars().count();
+    let width = source[span.clone()].chars().count().max(1);
+    anyhow::anyhow!(
+        "Invalid --where expression: {}\n  {}\n  {}{}",
+        message,
+        source,
+        " ".repeat(column),
+        "^".repeat(width)
+    )
+}
+
+/// A field of `record` whose name is close to `name`: the same apart from
+/// case, or at most two edits away.
+fn closest_field<'a>(name: &str, record: &'a Value) -> Option<&'a str> {
+    let fields = record.as_object()?;
+    let lower = name.to_lowercase();
+    fields
+        .keys()
+        .map(|key| {
+            let distance = if key.to_lowercase() == lower { 0 } else { edit_distance(&lower, &key.to_lowercase()) };
+            (distance, key)
+        })
+        .filter(|(distance, _)| *distance <= 2)
+        .min()
+        .map(|(_, key)| key.as_str())
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+    row[b.len()]
+}
+
+fn is_ident_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_'
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '.'
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, (Span, String)> {
+    let mut tokens = Vec::new();
+    let mut chars = source.char_indices().peekable();
+
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+
+        let rest = &source[start..];
+        let symbol = [
+            ("&&", TokenKind::And),
+            ("||", TokenKind::Or),
+            ("=~", TokenKind::Op(CompareOp::Matches)),
+            ("!~", TokenKind::Op(CompareOp::NotMatches)),
+            ("==", TokenKind::Op(CompareOp::Eq)),
+            ("!=", TokenKind::Op(CompareOp::Ne)),
+            ("<=", TokenKind::Op(CompareOp::Le)),
+            (">=", TokenKind::Op(CompareOp::Ge)),
+            ("=", TokenKind::Op(CompareOp::Eq)),
+            ("<", TokenKind::Op(CompareOp::Lt)),
+            (">", TokenKind::Op(CompareOp::Gt)),
+            ("!", TokenKind::Not),
+            ("(", TokenKind::LParen),
+            (")", TokenKind::RParen),
+            ("[", TokenKind::LBracket),
+            ("]", TokenKind::RBracket),
+            (",", TokenKind::Comma),
+        ]
+        .into_iter()
+        .find(|(text, _)| rest.starts_with(text));
+        if let Some((text, kind)) = symbol {
+            for _ in 0..text.len() {
+                chars.next();
+            }
+            tokens.push(Token { kind, span: start..start + text.len() });
+            continue;
+        }
+
+        if c == '"' || c == '\'' {
+            chars.next();
+            let mut text = String::new();
+            let mut end = None;
+            while let Some((i, ch)) = chars.next() {
+                match ch {
+                    '\\' => match chars.next() {
+                        Some((_, escaped)) => text.push(escaped),
+                        None => break,
+                    },
+                    ch if ch == c => {
+                        end = Some(i + 1);
+                        break;
+                    }
+                    ch => text.push(ch),
+                }
+            }
+            let end = end.ok_or_else(|| (start..source.len(), "Unterminated string".to_string()))?;
+            tokens.push(Token { kind: TokenKind::Str(text), span: start..end });
+            continue;
+        }
+
+        let negative_number = c == '-' && rest[1..].starts_with(|d: char| d.is_ascii_digit());
+        if c.is_ascii_digit() || negative_number {
+            let mut end = start + c.len_utf8();
+            chars.next();
+            let mut previous = c;
+            while let Some(&(i, ch)) = chars.peek() {
+                let exponent_sign = (ch == '+' || ch == '-') && (previous == 'e' || previous == 'E');
+                if ch.is_ascii_alphanumeric() || ch == '.' || exponent_sign {
+                    end = i + ch.len_utf8();
+                    previous = ch;
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            let text = &source[start..end];
+            if Literal::parse(text).type_name() != "number" {
+                return Err((start..end, format!("Invalid number `{}`", text)));
+            }
+            tokens.push(Token { kind: TokenKind::Number(text.to_string()), span: start..end });
+            continue;
+        }
+
+        if is_ident_start(c) {
+            let mut end = start;
+            while let Some(&(i, ch)) = chars.peek() {
+                // `[*]` or `[0]` right after a name is part of a path
+                let index_len = (ch == '[')
+                    .then(|| source[i..].find(']'))
+                    .flatten()
+                    .filter(|&close| close > 1 && (&source[i + 1..i + close] == "*" || source[i + 1..i + close].bytes().all(|b| b.is_ascii_digit())));
+                if let Some(close) = index_len {
+                    end = i + close + 1;
+                    while chars.peek().is_some_and(|&(j, _)| j < end) {
+                        chars.next();
+                    }
+                } else if is_ident_char(ch) {
+                    end = i + ch.len_utf8();
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            let word = &source[start..end];
+            let kind = match word.to_ascii_lowercase().as_str() {
+                "and" => TokenKind::And,
+                "or" => TokenKind::Or,
+                "not" => TokenKind::Not,
+                "in" => TokenKind::In,
+                "true" => TokenKind::True,
+                "false" => TokenKind::False,
+                "null" => TokenKind::Null,
+                "contains" => TokenKind::Op(CompareOp::Contains),
+                "like" => TokenKind::Op(CompareOp::Like),
+                _ => TokenKind::Ident(word.to_string()),
+            };
+            tokens.push(Token { kind, span: start..end });
+            continue;
+        }
+
+        return Err((start..start + c.len_utf8(), format!("Unexpected character `{}`", c)));
+    }
+
+    tokens.push(Token { kind: TokenKind::End, span: source.len()..source.len() });
+    Ok(tokens)
+}
+
+type ParseResult<T> = Result<T, (Span, String)>;
+
+/// Recursive descent over the grammar:
+///
+/// ```text
+/// or         = and ("or" and)*
+/// and        = unary ("and" unary)*
+/// unary      = "not" unary | "(" or ")" | comparison
+/// comparison = FIELD OP value | FIELD ["not"] "in" list
+///            | FIELD "contains" (value | list)
+///            | ("exists" | "missing") "(" FIELD ")"
+/// FIELD      = name ("." name | "[*]" | "[" index "]")*
+/// list       = ("[" | "(") value ("," value)* ("]" | ")")
+/// ```
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.pos]
+    }
+
+    fn next(&mut self) -> Token {
+        let token = self.tokens[self.pos].clone();
+        if token.kind != TokenKind::End {
+            self.pos += 1;
+        }
+        token
+    }
+
+    fn unexpected<T>(&self, expected: &str) -> ParseResult<T> {
+        let token = self.peek();
+        Err((token.span.clone(), format!("Expected {}, found {}", expected, token.kind)))
+    }
+
+    fn parse(&mut self) -> ParseResult<Node> {
+        let node = self.parse_or()?;
+        if self.peek().kind != TokenKind::End {
+            return self.unexpected("`and`, `or` or end of expression");
+        }
+        Ok(node)
+    }
+
+    fn parse_or(&mut self) -> ParseResult<Node> {
+        let mut node = self.parse_and()?;
+        while self.peek().kind == TokenKind::Or {
+            self.next();
+            node = Node::Or(Box::new(node), Box::new(self.parse_and()?));
+        }
+        Ok(node)
+    }
+
+    fn parse_and(&mut self) -> ParseResult<Node> {
+        let mut node = self.parse_unary()?;
+        while self.peek().kind == TokenKind::And {
+            self.next();
+            node = Node::And(Box::new(node), Box::new(self.parse_unary()?));
+        }
+        Ok(node)
+    }
+
+    fn parse_unary(&mut self) -> ParseResult<Node> {
+        match self.peek().kind {
+            TokenKind::Not => {
+                self.next();
+                Ok(Node::Not(Box::new(self.parse_unary()?)))
+            }
+            TokenKind::LParen => {
+                self.next();
+                let node = self.parse_or()?;
+                if self.peek().kind != TokenKind::RParen {
+                    return self.unexpected("`)` to close the group");
+                }
+                self.next();
+                Ok(node)
+            }
+            _ => self.parse_comparison(),
+        }
+    }
+
+    fn parse_comparison(&mut self) -> ParseResult<Node> {
+        let TokenKind::Ident(field) = self.peek().kind.clone() else {
+            return self.unexpected("a field name, `not` or `(`");
+        };
+        let field_span = self.next().span;
+
+        let existence = [CompareOp::Exists, CompareOp::Missing]
+            .into_iter()
+            .find(|op| field.eq_ignore_ascii_case(op.symbol()));
+        if let Some(op) = existence.filter(|_| self.peek().kind == TokenKind::LParen) {
+            self.next();
+            let TokenKind::Ident(field) = self.peek().kind.clone() else {
+                return self.unexpected(&format!("a field name in `{}(..)`", op.symbol()));
+            };
+            let field_span = self.next().span;
+            if self.peek().kind != TokenKind::RParen {
+                return self.unexpected("`)`");
+            }
+            self.next();
+            let predicate = Predicate::existence(&field, op).map_err(|e| (field_span.clone(), format!("{:#}", e)))?;
+            return Ok(Node::Compare(Comparison {
+                predicate,
+                field_span,
+                value_spans: Vec::new(),
+            }));
+        }
+
+        let op = match self.peek().kind {
+            TokenKind::Op(op) => {
+                self.next();
+                op
+            }
+            TokenKind::In => {
+                self.next();
+                CompareOp::In
+            }
+            TokenKind::Not if self.tokens[self.pos + 1].kind == TokenKind::In => {
+                self.next();
+                self.next();
+                CompareOp::NotIn
+            }
+            _ => return self.unexpected(&format!("a comparison operator after `{}`", field)),
+        };
+
+        let mut values = Vec::new();
+        let mut value_spans = Vec::new();
+        let list_close = match self.peek().kind {
+            TokenKind::LBracket => Some(TokenKind::RBracket),
+            TokenKind::LParen => Some(TokenKind::RParen),
+            _ => None,
+        };
+        let takes_list = matches!(op, CompareOp::In | CompareOp::NotIn | CompareOp::Contains);
+        if matches!(op, CompareOp::In | CompareOp::NotIn) && list_close.is_none() {
+            return self.unexpected("a list such as [1207, 1210]");
+        }
+        if let Some(close) = list_close.filter(|_| takes_list) {
+            self.next();
+            loop {
+                let (value, span) = self.parse_value()?;
+                values.push(value);
+                value_spans.push(span);
+                match self.peek().kind {
+                    TokenKind::Comma => {
+                        self.next();
+                    }
+                    ref kind if *kind == close => {
+                        self.next();
+                        break;
+                    }
+                    _ => return self.unexpected(&format!("`,` or {}", close)),
+                }
+            }
+        } else {
+            let (value, span) = self.parse_value()?;
+            values.push(value);
+            value_spans.push(span);
+        }
+
+        let path_error = FieldPath::parse(&field).err();
+        if let Some(e) = path_error {
+            return Err((field_span, format!("{:#}", e)));
+        }
+        let predicate = Predicate::new(&field, op, values)
+            .map_err(|e| (value_spans[0].start..value_spans[value_spans.len() - 1].end, format!("{:#}", e)))?;
+        Ok(Node::Compare(Comparison {
+            predicate,
+            field_span,
+            value_spans,
+        }))
+    }
+
+    fn parse_value(&mut self) -> ParseResult<(Literal, Span)> {
+        let token = self.peek().clone();
+        let literal = match &token.kind {
+            TokenKind::Str(text) => Literal::string(text),
+            TokenKind::Number(text) => Literal::parse(text),
+            TokenKind::True => Literal::parse("true"),
+            TokenKind::False => Literal::parse("false"),
+            TokenKind::Null => Literal::parse("null"),
+            TokenKind::Ident(word) => {
+                return Err((
+                    token.span,
+                    format!("Expected a value, found {}; quote text values: \"{}\"", token.kind, word),
+                ))
+            }
+            _ => return self.unexpected("a value"),
+        };
+        self.next();
+        Ok((literal, token.span))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_where_expression() {
+        let ms_error = json!({"component": "MS", "level": "ERROR", "serviceID": 230, "clusterFault.severity": null});
+        let ms_info = json!({"component": "MS", "level": "INFO", "serviceID": 110, "clusterFault.severity": null});
+        let critical = json!({"component": "API", "level": "INFO", "serviceID": null, "clusterFault.severity": "Critical"});
+        let records = vec![ms_error.clone(), ms_info.clone(), critical.clone()];
+
+        let expression = Expression::parse(r#"(component == "MS" and level == "ERROR") or clusterFault.severity == "Critical""#).unwrap();
+        expression.check(&records).unwrap();
+        assert!(expression.matches(&ms_error) && !expression.matches(&ms_info) && expression.matches(&critical));
+
+        // not binds tighter than and, and tighter than or
+        let expression = Expression::parse(r#"not level == "ERROR" and serviceID in [110, 230] || serviceID = null"#).unwrap();
+        assert!(!expression.matches(&ms_error) && expression.matches(&ms_info) && expression.matches(&critical));
+        assert!(Expression::parse("serviceID not in (230) && !(level != 'INFO')").unwrap().matches(&critical));
+
+        let error = |source: &str| format!("{:#}", Expression::parse(source).unwrap_err());
+        assert_eq!(error(r#"level == "ERROR" and"#),
+            "Invalid --where expression: Expected a field name, `not` or `(`, found end of expression\n  level == \"ERROR\" and\n                      ^");
+        assert!(error("level == ERROR").contains("quote text values: \"ERROR\"\n  level == ERROR\n           ^^^^^"));
+        assert!(error(r#"(level == "ERROR""#).contains("Expected `)` to close the group"));
+        assert!(error("serviceID >> 5").contains("Expected a value, found `>`"));
+        assert!(error(r#"level == "ERROR"#).contains("Unterminated string"));
+
+        let check = |source: &str| format!("{:#}", Expression::parse(source).unwrap().check(&records).unwrap_err());
+        assert_eq!(check(r#"serviceID == "230""#),
+            "Invalid --where expression: `serviceID` holds numbers, so \"230\" (string) never matches\n  serviceID == \"230\"\n               ^^^^^");
+        assert!(check("servceID == 230").contains("Unknown field `servceID` (did you mean `serviceID`?)"));
+        // Text fields compare against the value as written, so numbers are fine there
+        Expression::parse("level == 5").unwrap().check(&records).unwrap();
+    }
+
+    #[test]
+    fn test_text_operators() {
+        let record = json!({"content": "Deleting empty group snapshot groupSnapshotID=13933489", "class": "SliceSnapshots",
+            "serviceID": 230});
+
+        let expression = Expression::parse(r#"content =~ "snapshot group.*ID" or content contains ["PURGE", "empty group"]"#).unwrap();
+        assert!(expression.matches(&record));
+        assert!(Expression::parse(r#"class like "*Snapshots" and not content !~ "^Deleting""#).unwrap().matches(&record));
+
+        let error = format!("{:#}", Expression::parse(r#"content =~ "delet(""#).unwrap_err());
+        assert!(error.starts_with("Invalid --where expression: Invalid regex `delet(`"), "{}", error);
+        assert!(error.ends_with("\n             ^^^^^^^^"), "{}", error);
+        let error = format!("{:#}", Expression::parse(r#"serviceID =~ "23""#).unwrap().check(std::slice::from_ref(&record)).unwrap_err());
+        assert!(error.contains("`serviceID` holds numbers, but `=~` only matches text"), "{}", error);
+    }
+
+    #[test]
+    fn test_paths_and_existence() {
+        let record = json!({"clusterFault.severity": "Critical", "params": {"volumes": ["1207", "1210"], "drives": [{"id": 73, "slot": 4}]},
+            "driveIDs": [71, 73], "serviceID": null, "method": "ListVolumes"});
+        let records = vec![record.clone()];
+        let expression = Expression::parse(r#"params.volumes[*] == "1210" and driveIDs contains 73 and not exists(serviceID) and missing(sliceID)"#).unwrap();
+        expression.check(&records).unwrap();
+        assert!(expression.matches(&record));
+        assert!(Expression::parse("exists(params.drives[0].id) && params.drives[0].id >= 73").unwrap().matches(&record));
+        let check = |source: &str| format!("{:#}", Expression::parse(source).unwrap().check(&records).unwrap_err());
+        assert!(check(r#"params.drives[*].id == "73""#).contains("`params.drives[*].id` holds numbers, so \"73\" (string) never matches"));
+        assert!(check(r#"driveIDs =~ "7""#).contains("`driveIDs` holds arrays, but `=~` only matches text"));
+        assert!(check("params.drive[0].id == 73").contains("Unknown field `params.drive[0].id`"));
+    }
+}
@@ -0,0 +1,144 @@
+use anyhow::Result;
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use crate::input::read_records;
+use crate::output::is_stdio;
+use crate::{FilterStats, OutputOptions, SolidFireFilter};
+
+/// Most outputs one pass can feed: each record's matches are kept as bits
+/// of a `u64`.
+pub const MAX_ROUTES: usize = 64;
+
+/// One named filter and where its matches go.
+#[derive(Debug)]
+pub struct Route {
+    pub name: String,
+    pub filter: SolidFireFilter,
+    pub output: PathBuf,
+}
+
+/// What one route wrote.
+#[derive(Debug, Clone)]
+pub struct RouteStats {
+    pub name: String,
+    pub output: PathBuf,
+    pub stats: FilterStats,
+}
+
+/// Run every route over `input_path` with one read of the input: each
+/// record is parsed once and tested against all filters in the same
+/// parallel pass, then each route's matches are written to its own file.
+///
+/// The returned totals sum the routes' counts. On Ctrl-C the route being
+/// written is handled as in `filter_file` and later routes are skipped.
+pub fn fan_out(input_path: &Path, routes: &[Route], options: &OutputOptions) -> Result<(FilterStats, Vec<RouteStats>)> {
+    let start_time = Instant::now();
+    if routes.len() > MAX_ROUTES {
+        anyhow::bail!("Too many outputs: {} (at most {} per pass)", routes.len(), MAX_ROUTES);
+    }
+    let mut outputs: HashMap<&Path, &str> = HashMap::new();
+    for route in routes {
+        if let Some(other) = outputs.insert(&route.output, &route.name) {
+            anyhow::bail!("Outputs `{}` and `{}` both write to {}", other, route.name, route.output.display());
+        }
+    }
+
+    status!("🔍 SolidFire Log Filter v{}", env!("CARGO_PKG_VERSION"));
+    status!("🔀 Fanning out {} to {} outputs",
+        if is_stdio(input_path) { "<stdin>".to_string() } else { input_path.display().to_string() },
+        routes.len());
+    for route in routes {
+        status!("── {} → {}", route.name, route.output.display());
+        route.filter.print_criteria();
+    }
+
+    let records = read_records(input_path)?;
+    for route in routes {
+        route.filter.check(&records)?;
+    }
+    status!("📝 Processing {} records...", records.len());
+
+    let masks: Vec<u64> = records
+        .par_iter()
+        .map(|record| {
+            routes
+                .iter()
+                .enumerate()
+                .filter(|(_, route)| route.filter.filter_record(record))
+                .fold(0, |mask, (i, _)| mask | 1 << i)
+        })
+        .collect();
+
+    let mut total = FilterStats {
+        records_in: records.len(),
+        ..FilterStats::default()
+    };
+    let mut route_stats = Vec::with_capacity(routes.len());
+    for (i, route) in routes.iter().enumerate() {
+        let matched: Vec<bool> = masks.iter().map(|mask| mask & (1 << i) != 0).collect();
+        let mut stats = route.filter.write_matches(&records, &matched, &route.output, options)?;
+        stats.records_in = records.len();
+        total.records_matched += stats.records_matched;
+        total.records_context += stats.records_context;
+        total.records_written += stats.records_written;
+        if let Some(first) = &stats.time_span.first {
+            total.time_span.observe(first);
+        }
+        if let Some(last) = &stats.time_span.last {
+            total.time_span.observe(last);
+        }
+        total.interrupted = stats.interrupted;
+        route_stats.push(RouteStats {
+            name: route.name.clone(),
+            output: route.output.clone(),
+            stats,
+        });
+        if total.interrupted {
+            break;
+        }
+    }
+    total.duration = start_time.elapsed();
+    if total.interrupted {
+        return Ok((total, route_stats));
+    }
+
+    status!("✅ Filtered {} records into {} outputs in {:.2?} ({:.0} records/sec)",
+        records.len(), routes.len(), total.duration, records.len() as f64 / total.duration.as_secs_f64());
+    for route in &route_stats {
+        status!("   {:<20} {:>9} records → {}", route.name, route.stats.records_written, route.output.display());
+    }
+    Ok((total, route_stats))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expr::Expression;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_rejects_bad_routes() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("input.json");
+        fs::write(&input_path, r#"[{"level": "ERROR", "timestamp": "2025-06-12T08:30:00.000000Z"}]"#).unwrap();
+        let route = |name: &str, output: &str, expression: &str| {
+            let filter = SolidFireFilter::new(None, None, None, None, Vec::new(), None).unwrap()
+                .with_expression(Expression::parse(expression).unwrap());
+            Route { name: name.to_string(), filter, output: temp_dir.path().join(output) }
+        };
+
+        let duplicate = [route("a", "same.json", r#"level == "ERROR""#), route("b", "same.json", r#"level == "INFO""#)];
+        let error = fan_out(&input_path, &duplicate, &OutputOptions::default()).unwrap_err().to_string();
+        assert!(error.contains("Outputs `a` and `b` both write to"), "{}", error);
+
+        // Unknown fields are caught before anything is written
+        let typo = [route("ok", "ok.json", r#"level == "ERROR""#), route("typo", "typo.json", r#"levl == "ERROR""#)];
+        let error = format!("{:#}", fan_out(&input_path, &typo, &OutputOptions::default()).unwrap_err());
+        assert!(error.contains("Unknown field `levl`"), "{}", error);
+        assert!(!temp_dir.path().join("ok.json").exists());
+    }
+}
@@ -0,0 +1,69 @@
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+use crate::output::is_stdio;
+
+/// Load records from `path`, or from stdin when `path` is `-`.
+///
+/// Accepts both a JSON array (sf-parser's default output) and NDJSON
+/// (`--format ndjson`, `--follow`); the first non-whitespace byte decides.
+pub fn read_records(path: &Path) -> Result<Vec<Value>> {
+    if is_stdio(path) {
+        return parse_records(io::stdin().lock());
+    }
+    let file = File::open(path)
+        .with_context(|| format!("Failed to open input file: {}", path.display()))?;
+    parse_records(BufReader::new(file))
+}
+
+pub fn parse_records<R: BufRead>(mut reader: R) -> Result<Vec<Value>> {
+    let is_array = loop {
+        let buf = reader.fill_buf()?;
+        match buf.iter().position(|b| !b.is_ascii_whitespace()) {
+            Some(pos) => {
+                let first = buf[pos];
+                reader.consume(pos);
+                break first == b'[';
+            }
+            None if buf.is_empty() => return Ok(Vec::new()),
+            None => {
+                let len = buf.len();
+                reader.consume(len);
+            }
+        }
+    };
+
+    if is_array {
+        let json_data: Value = serde_json::from_reader(reader)
+            .with_context(|| "Failed to parse JSON input")?;
+        match json_data {
+            Value::Array(records) => Ok(records),
+            _ => anyhow::bail!("Input JSON must be an array of records"),
+        }
+    } else {
+        serde_json::Deserializer::from_reader(reader)
+            .into_iter::<Value>()
+            .enumerate()
+            .map(|(i, record)| record.with_context(|| format!("Failed to parse NDJSON record {}", i + 1)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_array_and_ndjson() {
+        let array = parse_records(&b"  [{\"line_num\": 1}, {\"line_num\": 2}]\n"[..]).unwrap();
+        let ndjson = parse_records(&b"{\"line_num\": 1}\n{\"line_num\": 2}\n"[..]).unwrap();
+
+        assert_eq!(array, ndjson);
+        assert_eq!(array.len(), 2);
+        assert!(parse_records(&b""[..]).unwrap().is_empty());
+        assert!(parse_records(&b"{\"line_num\": 1}\n{oops"[..]).is_err());
+    }
+}
@@ -1,19 +1,73 @@
 use anyhow::{Context, Result};
-use chrono::{NaiveDate, NaiveTime};
-use clap::{Arg, Command};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use clap::{Arg, ArgMatches, Command};
 use rayon::prelude::*;
 use serde_json::Value;
-use std::fs::File;
-use std::io::{BufReader, Write};
-use std::path::Path;
-use std::time::Instant;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-#[derive(Debug)]
+/// Set by `--quiet` to silence `status!` output.
+pub static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// Print human-oriented progress to stderr unless `--quiet` was given, so
+/// stdout stays free for piped output.
+macro_rules! status {
+    ($($arg:tt)*) => {
+        if !$crate::QUIET.load(std::sync::atomic::Ordering::Relaxed) {
+            eprintln!($($arg)*);
+        }
+    };
+}
+
+mod context;
+mod duration;
+mod expr;
+mod fanout;
+mod input;
+mod interrupt;
+mod output;
+mod path;
+mod predicate;
+mod preset;
+mod projection;
+mod sampling;
+mod severity;
+mod summary;
+
+pub use context::{ContextOptions, ContextScope, Selection};
+use duration::parse_duration;
+pub use expr::Expression;
+pub use fanout::{fan_out, Route, RouteStats, MAX_ROUTES};
+use input::read_records;
+use output::{is_broken_pipe, is_stdio, OutputTarget};
+pub use predicate::{CompareOp, Literal, Predicate};
+pub use preset::{Preset, Presets, DEFAULT_PRESETS_FILE};
+pub use projection::Projection;
+pub use sampling::Sampling;
+use severity::{record_severity, severity_rank, SEVERITY_NAMES};
+use summary::{RunStatus, RunSummary, TimeSpan, EXIT_FAILURE, EXIT_OK};
+
+/// Time criteria. `since`/`until` bound the record's full `timestamp`.
+/// A start (or end) date given with a start (or end) time bounds the range
+/// at that moment, so 06-12 22:00 to 06-13 02:00 is one overnight window.
+/// A time without a date is a time-of-day bound checked on every day; with
+/// no dates at all, a time-of-day range whose start is after its end
+/// (22:00-02:00) wraps past midnight.
+#[derive(Debug, Default)]
 pub struct TimeFilter {
     start_date: Option<NaiveDate>,
     end_date: Option<NaiveDate>,
     start_time: Option<NaiveTime>,
     end_time: Option<NaiveTime>,
+    /// `start_date` at `start_time`, when both are given
+    start_at: Option<NaiveDateTime>,
+    /// `end_date` at `end_time`, when both are given
+    end_at: Option<NaiveDateTime>,
+    since: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
 }
 
 impl TimeFilter {
@@ -53,15 +107,34 @@ impl TimeFilter {
             None
         };
 
+        let start_at = start_date.zip(start_time).map(|(date, time)| date.and_time(time));
+        let end_at = end_date.zip(end_time).map(|(date, time)| date.and_time(time));
         Ok(TimeFilter {
             start_date,
             end_date,
             start_time,
             end_time,
+            start_at,
+            end_at,
+            since: None,
+            until: None,
         })
     }
 
     pub fn matches(&self, record: &Value) -> bool {
+        // Check the absolute range against the full timestamp
+        if self.since.is_some() || self.until.is_some() {
+            let timestamp = record.get("timestamp")
+                .and_then(|v| v.as_str())
+                .and_then(|s| DateTime::parse_from_rfc3339(s).ok());
+            let Some(timestamp) = timestamp else {
+                return false;
+            };
+            if self.since.is_some_and(|since| timestamp < since) || self.until.is_some_and(|until| timestamp > until) {
+                return false;
+            }
+        }
+
         // Extract date and time from the record
         let date_str = record.get("date")
             .and_then(|v| v.as_str())
@@ -102,13 +175,30 @@ impl TimeFilter {
             }
         }
 
-        // Check time range (only if no date filters, or if date is within range)
-        if let (Some(record_time), Some(start_time)) = (record_time, self.start_time) {
+        if let (Some(record_date), Some(record_time)) = (record_date, record_time) {
+            let at = record_date.and_time(record_time);
+            if self.start_at.is_some_and(|start| at < start) || self.end_at.is_some_and(|end| at > end) {
+                return false;
+            }
+        }
+
+        // Check time of day; a time given with its date is part of the
+        // bound above instead
+        let start_time = self.start_time.filter(|_| self.start_at.is_none());
+        let end_time = self.end_time.filter(|_| self.end_at.is_none());
+        let dated = self.start_date.is_some() || self.end_date.is_some();
+        if let (Some(record_time), Some(start_time), Some(end_time)) = (record_time, start_time, end_time) {
+            if start_time > end_time && !dated {
+                // Wraps past midnight: only the gap between end and start is out
+                return record_time >= start_time || record_time <= end_time;
+            }
+        }
+        if let (Some(record_time), Some(start_time)) = (record_time, start_time) {
             if record_time < start_time {
                 return false;
             }
         }
-        if let (Some(record_time), Some(end_time)) = (record_time, self.end_time) {
+        if let (Some(record_time), Some(end_time)) = (record_time, end_time) {
             if record_time > end_time {
                 return false;
             }
@@ -118,10 +208,39 @@ impl TimeFilter {
     }
 }
 
+/// How `filter_file` writes its results.
+#[derive(Debug, Clone, Default)]
+pub struct OutputOptions {
+    /// On interrupt, close and keep the records written so far
+    pub keep_partial: bool,
+    /// Set by the Ctrl-C handler; checked while writing
+    pub interrupt: Option<Arc<AtomicBool>>,
+}
+
+/// Statistics collected over a `filter_file` run.
+#[derive(Debug, Clone, Default)]
+pub struct FilterStats {
+    pub records_in: usize,
+    pub records_matched: usize,
+    /// Non-matching neighbours kept by `--before`/`--after`/`--context-time`
+    pub records_context: usize,
+    /// Less than `records_matched + records_context` only when interrupted
+    pub records_written: usize,
+    pub time_span: TimeSpan,
+    pub duration: Duration,
+    pub interrupted: bool,
+}
+
 #[derive(Debug)]
 pub struct SolidFireFilter {
     time_filter: Option<TimeFilter>,
-    field_filters: Vec<(String, String)>, // field_name, field_value
+    field_filters: Vec<Predicate>,
+    expression: Option<Expression>,
+    min_severity: Option<u8>,
+    context: ContextOptions,
+    projection: Projection,
+    sampling: Sampling,
+    invert: bool,
 }
 
 impl SolidFireFilter {
@@ -130,7 +249,8 @@ impl SolidFireFilter {
         end_date: Option<&str>,
         start_time: Option<&str>,
         end_time: Option<&str>,
-        field_filters: Vec<(String, String)>,
+        field_filters: Vec<Predicate>,
+        min_level: Option<&str>,
     ) -> Result<Self> {
         let time_filter = if start_date.is_some() || end_date.is_some() || start_time.is_some() || end_time.is_some() {
             Some(TimeFilter::new(start_date, end_date, start_time, end_time)?)
@@ -138,13 +258,125 @@ impl SolidFireFilter {
             None
         };
 
+        let min_severity = if let Some(level) = min_level {
+            Some(severity_rank(level)
+                .with_context(|| format!("Invalid minimum level: {} (expected one of: {})", level, SEVERITY_NAMES.join(", ")))?)
+        } else {
+            None
+        };
+
         Ok(SolidFireFilter {
             time_filter,
             field_filters,
+            expression: None,
+            min_severity,
+            context: ContextOptions::default(),
+            projection: Projection::default(),
+            sampling: Sampling::default(),
+            invert: false,
         })
     }
 
+    /// Also bound records by their full timestamp (`--since`/`--until`).
+    pub fn with_time_range(mut self, since: Option<DateTime<Utc>>, until: Option<DateTime<Utc>>) -> Result<Self> {
+        if let (Some(since), Some(until)) = (since, until) {
+            if since > until {
+                anyhow::bail!("--since {} is after --until {}", since.to_rfc3339(), until.to_rfc3339());
+            }
+        }
+        if since.is_some() || until.is_some() {
+            let time_filter = self.time_filter.get_or_insert_with(TimeFilter::default);
+            time_filter.since = since;
+            time_filter.until = until;
+        }
+        Ok(self)
+    }
+
+    /// Also require `expression` (`--where`), on top of the field filters.
+    pub fn with_expression(mut self, expression: Expression) -> Self {
+        self.expression = Some(expression);
+        self
+    }
+
+    /// Also keep the records around each match (`--before`, `--after`,
+    /// `--context-time`), marking each output record as match or context.
+    pub fn with_context_options(mut self, context: ContextOptions) -> Self {
+        self.context = context;
+        self
+    }
+
+    /// Limit the columns written (`--select`, `--exclude`,
+    /// `--prune-empty-columns`).
+    pub fn with_projection(mut self, projection: Projection) -> Self {
+        self.projection = projection;
+        self
+    }
+
+    /// Keep only some of the matches (`--sample`, `--every-nth`, `--limit`,
+    /// `--tail`).
+    pub fn with_sampling(mut self, sampling: Sampling) -> Result<Self> {
+        if let Some(fraction) = sampling.fraction {
+            if !(fraction > 0.0 && fraction <= 1.0) {
+                anyhow::bail!("Invalid sample fraction: {} (expected more than 0 and at most 1)", fraction);
+            }
+        }
+        if sampling.every_nth == Some(0) {
+            anyhow::bail!("--every-nth must be at least 1");
+        }
+        self.sampling = sampling;
+        Ok(self)
+    }
+
+    /// Match the records that fail the criteria instead (`-v/--invert`).
+    pub fn with_invert(mut self, invert: bool) -> Self {
+        self.invert = invert;
+        self
+    }
+
+    /// The active criteria as JSON, for the run summary.
+    pub fn criteria(&self) -> Value {
+        let time_filter = self.time_filter.as_ref();
+        serde_json::json!({
+            "start_date": time_filter.and_then(|t| t.start_date).map(|d| d.to_string()),
+            "end_date": time_filter.and_then(|t| t.end_date).map(|d| d.to_string()),
+            "start_time": time_filter.and_then(|t| t.start_time).map(|t| t.to_string()),
+            "end_time": time_filter.and_then(|t| t.end_time).map(|t| t.to_string()),
+            "since": time_filter.and_then(|t| t.since).map(|t| t.to_rfc3339()),
+            "until": time_filter.and_then(|t| t.until).map(|t| t.to_rfc3339()),
+            "min_level": self.min_severity.map(|s| SEVERITY_NAMES[s as usize]),
+            "fields": self.field_filters.iter()
+                .map(|p| serde_json::json!({"field": p.field, "op": p.op.symbol(), "value": p.value_text()}))
+                .collect::<Vec<_>>(),
+            "where": self.expression.as_ref().map(Expression::source),
+            "context": self.context.is_active().then(|| serde_json::json!({
+                "before": self.context.before,
+                "after": self.context.after,
+                "time_secs": self.context.time.map(|t| t.as_secs()),
+                "scope": self.context.scope.map(ContextScope::field),
+            })),
+            "invert": self.invert,
+            "sampling": self.sampling.is_active().then(|| serde_json::json!({
+                "limit": self.sampling.limit,
+                "tail": self.sampling.tail,
+                "fraction": self.sampling.fraction,
+                "seed": self.sampling.fraction.map(|_| self.sampling.seed),
+                "every_nth": self.sampling.every_nth,
+            })),
+            "columns": self.projection.is_active().then(|| serde_json::json!({
+                "select": self.projection.select_text,
+                "exclude": self.projection.exclude_text,
+                "prune_empty": self.projection.prune_empty,
+            })),
+        })
+    }
+
+    /// Whether `record` is a match: it meets every criterion, or with
+    /// `--invert` fails at least one.
     pub fn filter_record(&self, record: &Value) -> bool {
+        self.meets_criteria(record) != self.invert
+    }
+
+    fn meets_criteria(&self, record: &Value) -> bool {
         // Check time filter
         if let Some(ref time_filter) = self.time_filter {
             if !time_filter.matches(record) {
@@ -152,137 +384,388 @@ impl SolidFireFilter {
             }
         }
 
-        // Check field filters
-        for (field_name, field_value) in &self.field_filters {
-            if let Some(record_value) = record.get(field_name) {
-                match record_value {
-                    Value::String(s) => {
-                        if s != field_value {
-                            return false;
-                        }
-                    }
-                    Value::Number(n) => {
-                        if let Ok(target_num) = field_value.parse::<f64>() {
-                            if n.as_f64().unwrap_or(0.0) != target_num {
-                                return false;
-                            }
-                        } else {
-                            return false;
-                        }
-                    }
-                    Value::Bool(b) => {
-                        if let Ok(target_bool) = field_value.parse::<bool>() {
-                            if *b != target_bool {
-                                return false;
-                            }
-                        } else {
-                            return false;
-                        }
-                    }
-                    Value::Null => {
-                        if field_value != "null" {
-                            return false;
-                        }
-                    }
-                    _ => return false, // Skip complex types for now
-                }
-            } else {
-                return false; // Field doesn't exist
+        // Check severity threshold
+        if let Some(min_severity) = self.min_severity {
+            match record_severity(record) {
+                Some(severity) if severity >= min_severity => {}
+                _ => return false,
             }
         }
 
-        true
+        // Check field filters
+        if !self.field_filters.iter().all(|predicate| predicate.matches(record)) {
+            return false;
+        }
+
+        self.expression.as_ref().is_none_or(|expression| expression.matches(record))
     }
 
-    pub fn filter_file(&self, input_path: &Path, output_path: &Path) -> Result<()> {
+    /// Filter `input_path` into `output_path`. A Ctrl-C is not an error: the
+    /// partial run's statistics come back with `interrupted` set.
+    pub fn filter_file(&self, input_path: &Path, output_path: &Path, options: &OutputOptions) -> Result<FilterStats> {
         let start_time = Instant::now();
         
-        println!("🔍 SolidFire Log Filter v{}", env!("CARGO_PKG_VERSION"));
-        println!("📊 Filtering {} to {}", input_path.display(), output_path.display());
+        status!("🔍 SolidFire Log Filter v{}", env!("CARGO_PKG_VERSION"));
+        status!("📊 Filtering {} to {}",
+            if is_stdio(input_path) { "<stdin>".to_string() } else { input_path.display().to_string() },
+            if is_stdio(output_path) { "<stdout>".to_string() } else { output_path.display().to_string() });
+        self.print_criteria();
+
+        // Read and parse JSON
+        let records = read_records(input_path)?;
+        self.check(&records)?;
+        
+        status!("📝 Processing {} records...", records.len());
+        
+        // Filter records in parallel
+        let matched: Vec<bool> = records
+            .par_iter()
+            .map(|record| self.filter_record(record))
+            .collect();
         
+        let mut stats = self.write_matches(&records, &matched, output_path, options)?;
+        stats.records_in = records.len();
+        stats.duration = start_time.elapsed();
+        if stats.interrupted {
+            return Ok(stats);
+        }
+        
+        let filter_rate = records.len() as f64 / stats.duration.as_secs_f64();
+        
+        status!("✅ Filtered {} → {} records in {:.2?} ({:.0} records/sec)", 
+            records.len(), stats.records_written, stats.duration, filter_rate);
+        if self.context.is_active() {
+            status!("   {} matches, {} context records", stats.records_matched, stats.records_context);
+        }
+        if !is_stdio(output_path) {
+            status!("📁 Output saved to: {}", output_path.display());
+            
+            // Suggest nushell usage
+            status!("\n💡 Usage examples:");
+            status!("   nu -c 'open {} | where snapshotID != null | length'", output_path.display());
+            status!("   nu -c 'open {} | first 10'", output_path.display());
+        }
+        
+        Ok(stats)
+    }
+    
+    /// Check the criteria against the input before filtering it, so a
+    /// mistyped `--where` field fails instead of matching nothing.
+    pub fn check(&self, records: &[Value]) -> Result<()> {
+        match self.expression {
+            Some(ref expression) => expression.check(records),
+            None => Ok(()),
+        }
+    }
+    
+    /// Print the active criteria as status lines.
+    fn print_criteria(&self) {
         if let Some(ref time_filter) = self.time_filter {
-            println!("📅 Time filters active:");
+            status!("📅 Time filters active:");
             if let Some(start_date) = time_filter.start_date {
-                println!("   Start date: {}", start_date);
+                status!("   Start date: {}", start_date);
             }
             if let Some(end_date) = time_filter.end_date {
-                println!("   End date: {}", end_date);
+                status!("   End date: {}", end_date);
             }
             if let Some(start_time) = time_filter.start_time {
-                println!("   Start time: {}", start_time);
+                status!("   Start time: {}", start_time);
             }
             if let Some(end_time) = time_filter.end_time {
-                println!("   End time: {}", end_time);
+                status!("   End time: {}", end_time);
+            }
+            if let Some(since) = time_filter.since {
+                status!("   Since: {}", since.to_rfc3339());
             }
+            if let Some(until) = time_filter.until {
+                status!("   Until: {}", until.to_rfc3339());
+            }
+        }
+        
+        if let Some(min_severity) = self.min_severity {
+            status!("🚨 Minimum level: {}", SEVERITY_NAMES[min_severity as usize]);
         }
         
         if !self.field_filters.is_empty() {
-            println!("🔧 Field filters active:");
-            for (field, value) in &self.field_filters {
-                println!("   {} = {}", field, value);
+            status!("🔧 Field filters active:");
+            for predicate in &self.field_filters {
+                status!("   {}", predicate);
             }
         }
-
-        // Read and parse JSON
-        let file = File::open(input_path)
-            .with_context(|| format!("Failed to open input file: {}", input_path.display()))?;
-        let reader = BufReader::new(file);
         
-        let json_data: Value = serde_json::from_reader(reader)
-            .with_context(|| "Failed to parse JSON input")?;
+        if let Some(ref expression) = self.expression {
+            status!("🧮 Where: {}", expression);
+        }
+        
+        if self.invert {
+            status!("🔄 Inverted: keeping records that don't match");
+        }
         
-        let records = json_data.as_array()
-            .with_context(|| "Input JSON must be an array of records")?;
+        if self.context.is_active() {
+            status!("🧭 Context: {}", self.context.describe());
+        }
         
-        println!("📝 Processing {} records...", records.len());
+        if self.sampling.is_active() {
+            status!("🎲 Keeping: {}", self.sampling.describe());
+        }
         
-        // Filter records in parallel
-        let filtered_records: Vec<&Value> = records
-            .par_iter()
-            .filter(|record| self.filter_record(record))
+        if !self.projection.select_text.is_empty() {
+            status!("📐 Select: {}", self.projection.select_text.join(", "));
+        }
+        if !self.projection.exclude_text.is_empty() {
+            status!("📐 Exclude: {}", self.projection.exclude_text.join(", "));
+        }
+        if self.projection.prune_empty {
+            status!("📐 Dropping columns that are null in every output record");
+        }
+    }
+    
+    /// Write the records flagged in `matched`, plus their context, to
+    /// `output_path` as a JSON array. Fills in everything but `records_in`
+    /// and `duration`.
+    pub fn write_matches(&self, records: &[Value], matched: &[bool], output_path: &Path, options: &OutputOptions) -> Result<FilterStats> {
+        // Thin out the matches, then pull in context around them
+        let sampled;
+        let matched = if self.sampling.is_active() {
+            sampled = self.sampling.apply(matched);
+            status!("🎲 Kept {} of {} matches", sampled.iter().filter(|&&m| m).count(), matched.iter().filter(|&&m| m).count());
+            &sampled
+        } else {
+            matched
+        };
+        let records_matched = matched.iter().filter(|&&m| m).count();
+        let filtered_records: Vec<(&Value, Selection)> = self.context
+            .select(records, matched)
+            .into_iter()
+            .zip(records)
+            .filter(|(selection, _)| *selection != Selection::Skip)
+            .map(|(selection, record)| (record, selection))
             .collect();
+        let marked = self.context.is_active();
+        let columns = self.projection.is_active().then(|| {
+            let selected: Vec<&Value> = filtered_records.iter().map(|(record, _)| *record).collect();
+            self.projection.columns(&selected)
+        });
+        if let Some(ref columns) = columns {
+            status!("📐 Writing {} columns", columns.len());
+        }
         
-        // Write filtered results
-        let mut output_file = File::create(output_path)
-            .with_context(|| format!("Failed to create output file: {}", output_path.display()))?;
+        // Write filtered results to a temporary file that is moved into
+        // place once the array is closed
+        let mut output_file = BufWriter::new(OutputTarget::create(output_path)?);
+        let mut written = 0;
+        let mut time_span = TimeSpan::default();
         
         // Write JSON array
         writeln!(output_file, "[")?;
-        for (i, record) in filtered_records.iter().enumerate() {
+        for (i, (record, selection)) in filtered_records.iter().enumerate() {
+            if i % 1000 == 0 && interrupt::requested(&options.interrupt) {
+                break;
+            }
             if i > 0 {
                 writeln!(output_file, ",")?;
             }
-            let json = serde_json::to_string(record)?;
+            let marker = selection.marker().filter(|_| marked);
+            let json = match (&columns, record, marker) {
+                (Some(columns), _, _) => {
+                    Projection::to_json(columns, record, marker.map(|marker| (context::MARKER_FIELD, marker)))?
+                }
+                (None, Value::Object(fields), Some(marker)) => {
+                    let mut fields = fields.clone();
+                    fields.insert(context::MARKER_FIELD.to_string(), Value::from(marker));
+                    serde_json::to_string(&fields)?
+                }
+                _ => serde_json::to_string(record)?,
+            };
             write!(output_file, "  {}", json)?;
+            written += 1;
+            if let Some(timestamp) = record.get("timestamp").and_then(|v| v.as_str()) {
+                time_span.observe(timestamp);
+            }
         }
-        writeln!(output_file, "\n]")?;
-        
-        let duration = start_time.elapsed();
-        let filter_rate = records.len() as f64 / duration.as_secs_f64();
-        
-        println!("✅ Filtered {} → {} records in {:.2?} ({:.0} records/sec)", 
-            records.len(), filtered_records.len(), duration, filter_rate);
-        println!("📁 Output saved to: {}", output_path.display());
         
-        // Suggest nushell usage
-        println!("\n💡 Usage examples:");
-        println!("   nu -c 'open {} | where snapshotID != null | length'", output_path.display());
-        println!("   nu -c 'open {} | first 10'", output_path.display());
+        let interrupted = interrupt::requested(&options.interrupt);
+        if interrupted {
+            if options.keep_partial {
+                writeln!(output_file, "\n]")?;
+                output_file.into_inner().map_err(|e| e.into_error())?.commit()?;
+                status!("Kept partial output with {} records in {}", written, output_path.display());
+            }
+            // Otherwise dropping the writer discards the temporary file
+        } else {
+            writeln!(output_file, "\n]")?;
+            output_file.into_inner().map_err(|e| e.into_error())?.commit()?;
+        }
         
-        Ok(())
+        Ok(FilterStats {
+            records_in: 0,
+            records_matched,
+            records_context: filtered_records.len() - records_matched,
+            records_written: written,
+            time_span,
+            duration: Duration::ZERO,
+            interrupted,
+        })
     }
 }
 
-fn main() -> Result<()> {
-    let matches = Command::new("sf-filter")
+/// Parse a `--since`/`--until` datetime: RFC 3339 (`2025-06-12T22:00:00Z`,
+/// `+02:00` offsets), or a UTC date and time without a zone, with `T` or a
+/// space and optional seconds. A bare date is the start of that day, or its
+/// last instant when `end_of_day` is set, so `--until 2025-06-13` includes
+/// the whole day.
+fn parse_datetime(value: &str, end_of_day: bool) -> Result<DateTime<Utc>> {
+    let value = value.trim();
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(value) {
+        return Ok(datetime.with_timezone(&Utc));
+    }
+    let value = value.strip_suffix('Z').unwrap_or(value);
+    for format in ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M"] {
+        if let Ok(datetime) = NaiveDateTime::parse_from_str(value, format) {
+            return Ok(datetime.and_utc());
+        }
+    }
+    let date = NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .with_context(|| format!("Invalid datetime: {} (expected e.g. 2025-06-12T22:00:00Z)", value))?;
+    let time = if end_of_day {
+        NaiveTime::from_hms_micro_opt(23, 59, 59, 999_999).expect("valid time")
+    } else {
+        NaiveTime::MIN
+    };
+    Ok(date.and_time(time).and_utc())
+}
+
+/// Build the filter for one output from the command line and `preset`:
+/// options given on the command line override the preset's, while --field
+/// and --where add to its criteria, as does a route's own expression.
+/// --select replaces the preset's selection; --exclude adds to it.
+fn build_filter(matches: &ArgMatches, preset: &Preset, route_where: Option<&str>) -> Result<SolidFireFilter> {
+    let arg = |name: &str| matches.get_one::<String>(name).map(String::as_str);
+    
+    // Parse field filters
+    let field_filters = preset.field.iter()
+        .chain(matches.get_many::<String>("field").into_iter().flatten())
+        .map(|spec| Predicate::parse(spec))
+        .collect::<Result<Vec<_>>>()?;
+    
+    let mut filter = SolidFireFilter::new(
+        arg("start-date").or(preset.start_date.as_deref()),
+        arg("end-date").or(preset.end_date.as_deref()),
+        arg("start-time").or(preset.start_time.as_deref()),
+        arg("end-time").or(preset.end_time.as_deref()),
+        field_filters,
+        arg("min-level").or(preset.min_level.as_deref()),
+    )?;
+    let since = arg("since").or(preset.since.as_deref());
+    let until = arg("until").or(preset.until.as_deref());
+    let window = arg("window").or(preset.window.as_deref());
+    let (since, until) = match arg("around").or(preset.around.as_deref()) {
+        Some(_) if since.is_some() || until.is_some() => anyhow::bail!("--around can't be combined with --since or --until"),
+        Some(around) => {
+            let around = parse_datetime(around, false)?;
+            let window = chrono::Duration::from_std(parse_duration(window.unwrap_or("5m"))?)?;
+            (Some(around - window), Some(around + window))
+        }
+        None if window.is_some() => anyhow::bail!("--window needs --around"),
+        None => (
+            since.map(|s| parse_datetime(s, false)).transpose()?,
+            until.map(|s| parse_datetime(s, true)).transpose()?,
+        ),
+    };
+    filter = filter.with_time_range(since, until)?;
+    let expressions: Vec<&str> = [preset.where_expr.as_deref(), arg("where"), route_where].into_iter().flatten().collect();
+    let expression = match expressions.as_slice() {
+        [] => None,
+        [expression] => Some(expression.to_string()),
+        expressions => Some(expressions.iter().map(|e| format!("({})", e)).collect::<Vec<_>>().join(" and ")),
+    };
+    if let Some(expression) = expression {
+        filter = filter.with_expression(Expression::parse(&expression)?);
+    }
+    
+    let list = |name: &str| -> Vec<String> { matches.get_many::<String>(name).into_iter().flatten().cloned().collect() };
+    let select = if matches.contains_id("select") { list("select") } else { preset.select.clone() };
+    let exclude: Vec<String> = preset.exclude.iter().cloned().chain(list("exclude")).collect();
+    let prune_empty = matches.get_flag("prune-empty-columns") || preset.prune_empty_columns.unwrap_or(false);
+    filter = filter.with_projection(Projection::new(&select, &exclude, prune_empty)?);
+    
+    filter = filter
+        .with_invert(matches.get_flag("invert"))
+        .with_sampling(Sampling {
+            limit: matches.get_one::<usize>("limit").copied(),
+            tail: matches.get_one::<usize>("tail").copied(),
+            fraction: matches.get_one::<f64>("sample").copied(),
+            seed: matches.get_one::<u64>("seed").copied().unwrap_or(0),
+            every_nth: matches.get_one::<usize>("every-nth").copied(),
+        })?;
+    
+    let context = matches.get_one::<usize>("context").copied();
+    let context_by = arg("context-by").or(preset.context_by.as_deref());
+    Ok(filter.with_context_options(ContextOptions {
+        before: matches.get_one::<usize>("before").copied().or(context).or(preset.before).unwrap_or(0),
+        after: matches.get_one::<usize>("after").copied().or(context).or(preset.after).unwrap_or(0),
+        time: arg("context-time").or(preset.context_time.as_deref()).map(parse_duration).transpose()?,
+        scope: context_by
+            .map(|s| ContextScope::from_name(s).with_context(|| format!("Invalid context scope: {} (expected thread or hostname)", s)))
+            .transpose()?,
+    }))
+}
+
+/// `<input stem>-<suffix>.json` next to the input.
+fn default_output_path(input_path: &Path, suffix: &str) -> Result<PathBuf> {
+    if is_stdio(input_path) {
+        anyhow::bail!("Output `{}` needs a file name when reading stdin", suffix);
+    }
+    let mut output = input_path.to_path_buf();
+    let stem = output.file_stem().unwrap().to_str().unwrap();
+    output.set_file_name(format!("{}-{}.json", stem, suffix));
+    Ok(output)
+}
+
+/// Parse a `--route NAME:OUTPUT[:EXPR]`, e.g.
+/// `errors:data/errors.json:level == "ERROR"`.
+fn parse_route(spec: &str) -> Result<(String, PathBuf, Option<String>)> {
+    let mut parts = spec.splitn(3, ':');
+    let name = parts.next().unwrap_or_default().trim();
+    let output = parts.next().unwrap_or_default().trim();
+    if name.is_empty() || output.is_empty() {
+        anyhow::bail!("Invalid route: {} (expected NAME:OUTPUT or NAME:OUTPUT:EXPR)", spec);
+    }
+    let expression = parts.next().map(str::trim).filter(|e| !e.is_empty()).map(str::to_string);
+    Ok((name.to_string(), PathBuf::from(output), expression))
+}
+
+/// Parse a `--param name=value`.
+fn parse_param(param: &str) -> Result<(String, String)> {
+    match param.split_once('=') {
+        Some((name, value)) if !name.trim().is_empty() => Ok((name.trim().to_string(), value.to_string())),
+        _ => anyhow::bail!("Invalid parameter: {} (expected name=value)", param),
+    }
+}
+
+fn main() {
+    let code = match run() {
+        Ok(code) => code,
+        Err(e) if is_broken_pipe(&e) => EXIT_OK,
+        Err(e) => {
+            eprintln!("Error: {:?}", e);
+            EXIT_FAILURE
+        }
+    };
+    std::process::exit(code);
+}
+
+/// The command-line interface.
+fn cli() -> Command {
+    Command::new("sf-filter")
         .version(env!("CARGO_PKG_VERSION"))
         .about("Fast time-range and field filter for SolidFire parsed JSON logs")
         .arg(Arg::new("input")
-            .help("Input JSON file from sf-parser")
-            .required(true)
+            .help("Input JSON or NDJSON file from sf-parser, or - for stdin")
+            .required_unless_present("list-presets")
             .index(1))
         .arg(Arg::new("output")
-            .help("Output filtered JSON file")
+            .help("Output filtered JSON file, or - for stdout (default for stdin input)")
             .short('o')
             .long("output"))
         .arg(Arg::new("start-date")
@@ -294,59 +777,328 @@ fn main() -> Result<()> {
             .long("end-date")
             .value_name("DATE"))
         .arg(Arg::new("start-time")
-            .help("Start time (HH:MM:SS or HH:MM)")
+            .help("Start time of day (HH:MM:SS or HH:MM); after --end-time, the range wraps past midnight")
             .long("start-time")
             .value_name("TIME"))
         .arg(Arg::new("end-time")
             .help("End time (HH:MM:SS or HH:MM)")
             .long("end-time")
             .value_name("TIME"))
+        .arg(Arg::new("since")
+            .help("Only records at or after this datetime (e.g. 2025-06-12T22:00:00Z)")
+            .long("since")
+            .value_name("DATETIME"))
+        .arg(Arg::new("until")
+            .help("Only records at or before this datetime; a bare date includes the whole day")
+            .long("until")
+            .value_name("DATETIME"))
+        .arg(Arg::new("around")
+            .help("Only records within --window of this datetime")
+            .long("around")
+            .value_name("DATETIME")
+            .conflicts_with_all(["since", "until"]))
+        .arg(Arg::new("window")
+            .help("Half-width of the --around range (e.g. 30s, 5m, 1h) [default: 5m]")
+            .long("window")
+            .value_name("DURATION"))
         .arg(Arg::new("field")
-            .help("Field filter: field=value (can be used multiple times)")
+            .help("Field filter: field=value, field!=value, field>N (also <, <=, >=, =~, !~), 'field in a,b', 'field contains a,b', 'field like glob' or exists(field) / missing(field); fields may be paths like params.volumes[*] (can be used multiple times)")
             .long("field")
-            .value_name("FIELD=VALUE")
+            .value_name("FIELD<OP>VALUE")
+            .action(clap::ArgAction::Append))
+        .arg(Arg::new("where")
+            .help("Filter expression, e.g. '(component == \"MS\" and level == \"ERROR\") or serviceID in [230, 110]'")
+            .long("where")
+            .value_name("EXPR"))
+        .arg(Arg::new("before")
+            .help("Also keep N records before each match")
+            .short('B')
+            .long("before")
+            .value_name("N")
+            .value_parser(clap::value_parser!(usize)))
+        .arg(Arg::new("after")
+            .help("Also keep N records after each match")
+            .short('A')
+            .long("after")
+            .value_name("N")
+            .value_parser(clap::value_parser!(usize)))
+        .arg(Arg::new("context")
+            .help("Also keep N records before and after each match (--before/--after override)")
+            .short('C')
+            .long("context")
+            .value_name("N")
+            .value_parser(clap::value_parser!(usize)))
+        .arg(Arg::new("context-time")
+            .help("Also keep records within this long of each match (e.g. 30s, 5m)")
+            .long("context-time")
+            .value_name("DURATION"))
+        .arg(Arg::new("context-by")
+            .help("Only take context from the match's own thread or hostname")
+            .long("context-by")
+            .value_name("FIELD")
+            .value_parser(["thread", "hostname"]))
+        .arg(Arg::new("invert")
+            .help("Keep the records that don't match the criteria")
+            .short('v')
+            .long("invert")
+            .action(clap::ArgAction::SetTrue))
+        .arg(Arg::new("limit")
+            .help("Keep only the first N matches")
+            .long("limit")
+            .visible_alias("head")
+            .value_name("N")
+            .value_parser(clap::value_parser!(usize)))
+        .arg(Arg::new("tail")
+            .help("Keep only the last N matches")
+            .long("tail")
+            .value_name("N")
+            .value_parser(clap::value_parser!(usize))
+            .conflicts_with("limit"))
+        .arg(Arg::new("sample")
+            .help("Keep a random fraction of the matches, e.g. 0.01 (reproducible with --seed)")
+            .long("sample")
+            .value_name("FRACTION")
+            .value_parser(clap::value_parser!(f64)))
+        .arg(Arg::new("seed")
+            .help("Seed for --sample [default: 0]")
+            .long("seed")
+            .value_name("N")
+            .value_parser(clap::value_parser!(u64))
+            .requires("sample"))
+        .arg(Arg::new("every-nth")
+            .help("Keep every Nth match, starting with the first")
+            .long("every-nth")
+            .value_name("N")
+            .value_parser(clap::value_parser!(usize)))
+        .arg(Arg::new("select")
+            .help("Only write these columns, in this order (comma-separated; * and ? globs allowed)")
+            .long("select")
+            .value_name("COLUMNS")
+            .value_delimiter(',')
             .action(clap::ArgAction::Append))
-        .get_matches();
+        .arg(Arg::new("exclude")
+            .help("Leave out these columns (comma-separated; * and ? globs allowed)")
+            .long("exclude")
+            .value_name("COLUMNS")
+            .value_delimiter(',')
+            .action(clap::ArgAction::Append))
+        .arg(Arg::new("prune-empty-columns")
+            .help("Leave out columns that are null in every record written")
+            .long("prune-empty-columns")
+            .action(clap::ArgAction::SetTrue))
+        .arg(Arg::new("min-level")
+            .help("Minimum severity (debug, info, notice, warn, error, critical, alert, emergency)")
+            .long("min-level")
+            .value_name("LEVEL"))
+        .arg(Arg::new("preset")
+            .help("Run a named filter preset from the presets file; several write one output each in a single pass")
+            .long("preset")
+            .value_name("NAME")
+            .action(clap::ArgAction::Append))
+        .arg(Arg::new("route")
+            .help("Also write the records matching EXPR (a --where expression, on top of the shared criteria) to OUTPUT, in the same pass (can be used multiple times)")
+            .long("route")
+            .value_name("NAME:OUTPUT[:EXPR]")
+            .action(clap::ArgAction::Append))
+        .arg(Arg::new("param")
+            .help("Fill a ${name} placeholder of the preset (can be used multiple times)")
+            .long("param")
+            .value_name("NAME=VALUE")
+            .action(clap::ArgAction::Append))
+        .arg(Arg::new("presets")
+            .help("TOML file of [preset.NAME] tables (default: ./sf-filter-presets.toml if present)")
+            .long("presets")
+            .value_name("FILE"))
+        .arg(Arg::new("list-presets")
+            .help("List the presets in the presets file and exit")
+            .long("list-presets")
+            .action(clap::ArgAction::SetTrue))
+        .arg(Arg::new("keep-partial")
+            .help("If interrupted, close the records written so far into a valid JSON array")
+            .long("keep-partial")
+            .action(clap::ArgAction::SetTrue))
+        .arg(Arg::new("summary-json")
+            .help("Write a machine-readable run summary to this file")
+            .long("summary-json")
+            .value_name("FILE"))
+        .arg(Arg::new("quiet")
+            .help("Suppress progress and summary messages on stderr")
+            .short('q')
+            .long("quiet")
+            .action(clap::ArgAction::SetTrue))
+}
+
+/// Run the CLI and return the process exit code.
+fn run() -> Result<i32> {
+    let matches = cli().get_matches();
+    
+    QUIET.store(matches.get_flag("quiet"), std::sync::atomic::Ordering::Relaxed);
+    
+    let presets = match matches.get_one::<String>("presets") {
+        Some(path) => Presets::load(Path::new(path))?,
+        None if Path::new(DEFAULT_PRESETS_FILE).exists() => Presets::load(Path::new(DEFAULT_PRESETS_FILE))?,
+        None => Presets::default(),
+    };
+    if matches.get_flag("list-presets") {
+        for (name, description) in presets.list() {
+            println!("{:<20} {}", name, description.unwrap_or(""));
+        }
+        return Ok(EXIT_OK);
+    }
+    
+    let params = match matches.get_many::<String>("param") {
+        Some(params) => params.map(|param| parse_param(param)).collect::<Result<Vec<_>>>()?,
+        None => Vec::new(),
+    };
+    let preset_names: Vec<&str> = matches.get_many::<String>("preset").into_iter().flatten().map(String::as_str).collect();
+    let mut preset_list = match preset_names.as_slice() {
+        [] if !params.is_empty() => anyhow::bail!("--param needs a --preset to fill in"),
+        [] => Vec::new(),
+        names => {
+            status!("🧰 Preset: {}", names.join(", "));
+            presets.resolve_all(names, &params)?
+        }
+    };
+    let route_specs: Vec<&str> = matches.get_many::<String>("route").into_iter().flatten().map(String::as_str).collect();
     
     let input_path = Path::new(matches.get_one::<String>("input").unwrap());
-    let output_path = if let Some(output) = matches.get_one::<String>("output") {
+    let summary_path = matches.get_one::<String>("summary-json").map(PathBuf::from);
+    
+    // Several presets, or any --route: one pass, one output per filter
+    if preset_names.len() > 1 || !route_specs.is_empty() {
+        if matches.contains_id("output") {
+            anyhow::bail!("-o can't be used with several outputs; each --route and preset names its own");
+        }
+        let mut routes = Vec::new();
+        for (name, preset) in preset_names.iter().zip(&preset_list) {
+            let output = match preset.output.as_deref() {
+                Some(output) => PathBuf::from(output),
+                None => default_output_path(input_path, name)?,
+            };
+            routes.push(Route { name: name.to_string(), filter: build_filter(&matches, preset, None)?, output });
+        }
+        for spec in route_specs {
+            let (name, output, expression) = parse_route(spec)?;
+            routes.push(Route { name, filter: build_filter(&matches, &Preset::default(), expression.as_deref())?, output });
+        }
+        
+        let options = OutputOptions {
+            keep_partial: matches.get_flag("keep-partial"),
+            interrupt: Some(interrupt::install()?),
+        };
+        let (stats, route_stats) = match fan_out(input_path, &routes, &options) {
+            Ok(result) => result,
+            Err(e) => {
+                if let Some(ref summary_path) = summary_path {
+                    RunSummary::new(input_path, Path::new(""), Value::Null, RunStatus::Failed)
+                        .with_routes(&routes, &[])
+                        .with_error(format!("{:#}", e))
+                        .write(summary_path)?;
+                }
+                return Err(e);
+            }
+        };
+        let status = if stats.interrupted { RunStatus::Interrupted } else { RunStatus::Ok };
+        let error = stats.interrupted.then(|| format!("Interrupted after writing {} of {} outputs",
+            route_stats.len() - 1, routes.len()));
+        if let Some(ref summary_path) = summary_path {
+            let mut summary = RunSummary::new(input_path, Path::new(""), Value::Null, status)
+                .with_stats(&stats)
+                .with_routes(&routes, &route_stats);
+            summary.error = error.clone();
+            summary.write(summary_path)?;
+        }
+        if let Some(error) = error {
+            eprintln!("Error: {}", error);
+        }
+        return Ok(status.exit_code());
+    }
+    
+    let preset = preset_list.pop().unwrap_or_default();
+    let output_path = if let Some(output) = matches.get_one::<String>("output").or(preset.output.as_ref()) {
         Path::new(output).to_path_buf()
+    } else if is_stdio(input_path) {
+        PathBuf::from("-")
     } else {
-        let mut output = input_path.to_path_buf();
-        let stem = output.file_stem().unwrap().to_str().unwrap();
-        output.set_file_name(format!("{}-filtered.json", stem));
-        output
+        default_output_path(input_path, "filtered")?
     };
+    let filter = build_filter(&matches, &preset, None)?;
     
-    // Parse field filters
-    let mut field_filters = Vec::new();
-    if let Some(fields) = matches.get_many::<String>("field") {
-        for field_spec in fields {
-            if let Some((field, value)) = field_spec.split_once('=') {
-                field_filters.push((field.to_string(), value.to_string()));
-            } else {
-                anyhow::bail!("Invalid field filter format. Use: field=value");
+    let options = OutputOptions {
+        keep_partial: matches.get_flag("keep-partial"),
+        interrupt: Some(interrupt::install()?),
+    };
+    
+    let stats = match filter.filter_file(input_path, &output_path, &options) {
+        Ok(stats) => stats,
+        Err(e) => {
+            if let Some(ref summary_path) = summary_path {
+                RunSummary::new(input_path, &output_path, filter.criteria(), RunStatus::Failed)
+                    .with_error(format!("{:#}", e))
+                    .write(summary_path)?;
             }
+            return Err(e);
         }
-    }
+    };
     
-    let filter = SolidFireFilter::new(
-        matches.get_one::<String>("start-date").map(|s| s.as_str()),
-        matches.get_one::<String>("end-date").map(|s| s.as_str()),
-        matches.get_one::<String>("start-time").map(|s| s.as_str()),
-        matches.get_one::<String>("end-time").map(|s| s.as_str()),
-        field_filters,
-    )?;
+    let status = if stats.interrupted { RunStatus::Interrupted } else { RunStatus::Ok };
+    let error = stats.interrupted.then(|| format!("Interrupted after writing {} of {} records",
+        stats.records_written, stats.records_matched + stats.records_context));
     
-    filter.filter_file(input_path, &output_path)?;
+    if let Some(ref summary_path) = summary_path {
+        let mut summary = RunSummary::new(input_path, &output_path, filter.criteria(), status)
+            .with_stats(&stats);
+        summary.error = error.clone();
+        summary.write(summary_path)?;
+    }
     
-    Ok(())
+    if let Some(error) = error {
+        eprintln!("Error: {}", error);
+    }
+    Ok(status.exit_code())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use serde_json::json;
+    use std::fs;
+    use tempfile::TempDir;
+    
+    /// A temp directory with an `input.json` to run filters over.
+    struct Fixture {
+        dir: TempDir,
+        input: PathBuf,
+        output: PathBuf,
+    }
+    
+    impl Fixture {
+        fn new(input_json: &str) -> Fixture {
+            let dir = TempDir::new().unwrap();
+            let input = dir.path().join("input.json");
+            let output = dir.path().join("output.json");
+            fs::write(&input, input_json).unwrap();
+            Fixture { dir, input, output }
+        }
+        
+        fn with_records(records: &[Value]) -> Fixture {
+            Fixture::new(&serde_json::to_string(records).unwrap())
+        }
+        
+        /// Run `filter` over the input; returns the output document's text.
+        fn output(&self, filter: &SolidFireFilter) -> String {
+            filter.filter_file(&self.input, &self.output, &OutputOptions::default()).unwrap();
+            fs::read_to_string(&self.output).unwrap()
+        }
+        
+        fn records(&self, filter: &SolidFireFilter) -> Vec<Value> {
+            serde_json::from_str(&self.output(filter)).unwrap()
+        }
+        
+        fn line_nums(&self, filter: &SolidFireFilter) -> Vec<u64> {
+            self.records(filter).iter().map(|r| r["line_num"].as_u64().unwrap()).collect()
+        }
+    }
     
     #[test]
     fn test_time_filter() {
@@ -387,4 +1139,257 @@ mod tests {
         assert!(filter.matches(&record1));
         assert!(!filter.matches(&record2));
     }
+    
+    #[test]
+    fn test_time_ranges() {
+        let at = |timestamp: &str, time: &str| json!({"timestamp": timestamp, "date": &timestamp[..10], "time": time});
+        let before = at("2025-06-12T21:59:59.999999Z", "21:59:59.999999");
+        let late = at("2025-06-12T23:30:00.000000Z", "23:30:00.000000");
+        let early = at("2025-06-13T01:15:00.000000Z", "01:15:00.000000");
+        let midday = at("2025-06-13T12:00:00.000000Z", "12:00:00.000000");
+        
+        let since = parse_datetime("2025-06-12T22:00:00Z", false).unwrap();
+        let until = parse_datetime("2025-06-13", true).unwrap();
+        let filter = SolidFireFilter::new(None, None, None, None, Vec::new(), None).unwrap()
+            .with_time_range(Some(since), Some(until)).unwrap();
+        assert!(!filter.filter_record(&before) && filter.filter_record(&late) && filter.filter_record(&midday));
+        assert!(!filter.filter_record(&json!({"date": "2025-06-13", "time": "01:00:00"})));
+        
+        // Zone-less and offset forms are the same instant
+        assert_eq!(parse_datetime("2025-06-13 00:00", false).unwrap(), parse_datetime("2025-06-13T02:00:00+02:00", false).unwrap());
+        assert_eq!(until, parse_datetime("2025-06-13T23:59:59.999999", false).unwrap());
+        assert!(parse_datetime("13/06/2025", false).is_err());
+        assert!(SolidFireFilter::new(None, None, None, None, Vec::new(), None).unwrap()
+            .with_time_range(Some(until), Some(since)).is_err());
+        
+        // 22:00-02:00 wraps past midnight
+        let overnight = TimeFilter::new(None, None, Some("22:00"), Some("02:00")).unwrap();
+        assert!(!overnight.matches(&before) && overnight.matches(&late) && overnight.matches(&early) && !overnight.matches(&midday));
+        
+        // With dates, each time belongs to its date: one window from the 12th at 22:00 to the 13th at 02:00
+        let window = TimeFilter::new(Some("2025-06-12"), Some("2025-06-13"), Some("22:00"), Some("02:00")).unwrap();
+        assert!(!window.matches(&json!({"date": "2025-06-12", "time": "01:00:00"})));
+        assert!(window.matches(&json!({"date": "2025-06-12", "time": "23:00:00"})));
+        assert!(window.matches(&json!({"date": "2025-06-13", "time": "01:00:00"})));
+        assert!(!window.matches(&json!({"date": "2025-06-13", "time": "23:00:00"})));
+    }
+    
+    #[test]
+    fn test_min_level_filter() {
+        let filter = SolidFireFilter::new(None, None, None, None, Vec::new(), Some("warn")).unwrap();
+        
+        let error = json!({"level": "ERROR", "date": "2025-06-12", "time": "08:35:00"});
+        let notice = json!({"level": "APP-5", "severity_num": 2, "date": "2025-06-12", "time": "08:35:00"});
+        let app_warning = json!({"level": "APP-4", "date": "2025-06-12", "time": "08:35:00"});
+        let unknown = json!({"level": null, "date": "2025-06-12", "time": "08:35:00"});
+        
+        assert!(filter.filter_record(&error));
+        assert!(!filter.filter_record(&notice));
+        assert!(filter.filter_record(&app_warning));
+        assert!(!filter.filter_record(&unknown));
+        assert!(SolidFireFilter::new(None, None, None, None, Vec::new(), Some("loud")).is_err());
+    }
+    
+    #[test]
+    fn test_presets() {
+        let fixture = Fixture::new(r#"[
+            {"line_num": 1, "level": "WARN", "serviceID": 230, "timestamp": "2025-06-12T08:30:00.000000Z"},
+            {"line_num": 2, "level": "ERROR", "serviceID": 230, "timestamp": "2025-06-12T08:31:00.000000Z"},
+            {"line_num": 3, "level": "ERROR", "serviceID": 110, "timestamp": "2025-06-12T08:32:00.000000Z"},
+            {"line_num": 4, "level": "ERROR", "serviceID": 230, "timestamp": "2025-06-13T08:33:00.000000Z"}
+        ]"#);
+        let presets = Presets::parse(r#"
+            [preset.service]
+            params = { level = "warn" }
+            field = ["serviceID=${id}"]
+            min_level = "${level}"
+            since = "${day}T00:00:00Z"
+            until = "${day}"
+        "#).unwrap();
+        let params = [parse_param("id=230").unwrap(), parse_param("day=2025-06-12").unwrap()];
+        let preset = presets.resolve("service", &params).unwrap();
+        let lines = |args: &[&str]| {
+            let matches = cli().get_matches_from([&["sf-filter", "input.json"], args].concat());
+            fixture.line_nums(&build_filter(&matches, &preset, None).unwrap())
+        };
+        
+        assert_eq!(lines(&[]), [1, 2]);
+        // Options on the command line override the preset's; --field adds to it
+        assert_eq!(lines(&["--min-level", "error"]), [2]);
+        assert_eq!(lines(&["--field", "level=WARN"]), [1]);
+        assert!(parse_param("=230").is_err() && parse_param("id").is_err());
+    }
+    
+    #[test]
+    fn test_fan_out() {
+        let fixture = Fixture::new(r#"[
+            {"component": "MS", "level": "ERROR", "serviceID": 230, "timestamp": "2025-06-12T08:30:00.000000Z"},
+            {"component": "Snaps", "level": "INFO", "serviceID": 230, "timestamp": "2025-06-12T08:31:00.000000Z"},
+            {"component": "MS", "level": "INFO", "serviceID": 110, "timestamp": "2025-06-12T08:32:00.000000Z"}
+        ]"#);
+        let route = |spec: &str| {
+            let (name, output, expression) = parse_route(spec).unwrap();
+            let mut filter = SolidFireFilter::new(None, None, None, None, Vec::new(), None).unwrap();
+            if let Some(expression) = expression {
+                filter = filter.with_expression(Expression::parse(&expression).unwrap());
+            }
+            Route { name, filter, output: fixture.dir.path().join(output) }
+        };
+        let routes = vec![
+            route(r#"errors:errors.json:level == "ERROR""#),
+            route(r#"ms:ms.json:component == "MS""#),
+            route(r#"snaps:snaps.json:component == "Snaps" and serviceID == 230"#),
+            route("all:all.json"),
+        ];
+        
+        let (stats, route_stats) = fan_out(&fixture.input, &routes, &OutputOptions::default()).unwrap();
+        let counts: Vec<(&str, usize)> = route_stats.iter().map(|r| (r.name.as_str(), r.stats.records_written)).collect();
+        assert_eq!(counts, [("errors", 1), ("ms", 2), ("snaps", 1), ("all", 3)]);
+        assert_eq!((stats.records_in, stats.records_written), (3, 7));
+        let ms: Vec<Value> = serde_json::from_str(&fs::read_to_string(fixture.dir.path().join("ms.json")).unwrap()).unwrap();
+        assert_eq!((ms[0]["serviceID"].as_u64(), ms[1]["serviceID"].as_u64()), (Some(230), Some(110)));
+        
+        let summary = RunSummary::new(&fixture.input, Path::new(""), Value::Null, RunStatus::Ok).with_stats(&stats).with_routes(&routes, &route_stats);
+        let summary = serde_json::to_value(&summary).unwrap();
+        assert_eq!(summary["output"], Value::Null);
+        assert_eq!(summary["outputs"][2]["records_written"], 1);
+        assert_eq!(summary["outputs"][2]["criteria"]["where"], r#"component == "Snaps" and serviceID == 230"#);
+        assert!(parse_route("errors").is_err() && parse_route(":x.json").is_err());
+    }
+    
+    #[test]
+    fn test_projection() {
+        let fixture = Fixture::new(r#"[
+            {"timestamp": "2025-06-12T08:30:00.000000Z", "level": "ERROR", "content": "a", "raw_line": "x", "serviceID": 230, "clusterFault.severity": null, "clusterFault.code": null, "volumeID": null},
+            {"timestamp": "2025-06-12T08:31:00.000000Z", "level": "INFO", "content": "b", "raw_line": "y", "serviceID": null, "clusterFault.severity": "Critical", "clusterFault.code": null, "volumeID": null},
+            {"timestamp": "2025-06-12T08:32:00.000000Z", "level": "INFO", "content": "c", "raw_line": "z", "serviceID": 110, "clusterFault.severity": null, "clusterFault.code": null, "volumeID": 1210}
+        ]"#);
+        let write = |projection: Projection, predicates: Vec<Predicate>| {
+            fixture.output(&SolidFireFilter::new(None, None, None, None, predicates, None).unwrap().with_projection(projection))
+        };
+        let columns = |output: &str| {
+            let records: Vec<Value> = serde_json::from_str(output).unwrap();
+            records[0].as_object().unwrap().keys().cloned().collect::<Vec<_>>().join(" ")
+        };
+        let strings = |items: &[&str]| items.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        
+        // --select keeps its own order, and a named column no record has becomes null
+        let output = write(Projection::new(&strings(&["level", "timestamp", "sliceID"]), &[], false).unwrap(), Vec::new());
+        assert!(output.contains(r#"{"level":"ERROR","timestamp":"2025-06-12T08:30:00.000000Z","sliceID":null}"#), "{}", output);
+        
+        // Pruning looks at the records written: volumeID is only set outside the subset
+        let output = write(Projection::new(&[], &[], true).unwrap(), vec![Predicate::parse("timestamp<2025-06-12T08:32").unwrap()]);
+        assert_eq!(columns(&output), "clusterFault.severity content level raw_line serviceID timestamp");
+        assert!(output.contains(r#""serviceID":null"#), "null kept where the column has values elsewhere");
+    }
+    
+    #[test]
+    fn test_sampling_and_invert() {
+        let records: Vec<Value> = (0..1000).map(|i| json!({"line_num": i, "level": if i % 10 == 0 { "ERROR" } else { "INFO" }})).collect();
+        let fixture = Fixture::with_records(&records);
+        let lines = |filter: SolidFireFilter| fixture.line_nums(&filter);
+        let errors = || SolidFireFilter::new(None, None, None, None, vec![Predicate::parse("level=ERROR").unwrap()], None).unwrap();
+        let sampled = |sampling: Sampling| lines(errors().with_sampling(sampling).unwrap());
+        
+        // Sampling thins the matches, not the input
+        assert_eq!(sampled(Sampling { limit: Some(3), ..Default::default() }), [0, 10, 20]);
+        assert_eq!(sampled(Sampling { every_nth: Some(25), tail: Some(2), ..Default::default() }), [500, 750]);
+        let sample = sampled(Sampling { fraction: Some(0.3), seed: 7, ..Default::default() });
+        assert!(!sample.is_empty() && sample.iter().all(|n| n % 10 == 0));
+        assert!(errors().with_sampling(Sampling { fraction: Some(1.5), ..Default::default() }).is_err());
+        assert!(errors().with_sampling(Sampling { every_nth: Some(0), ..Default::default() }).is_err());
+        
+        let inverted = lines(errors().with_invert(true));
+        assert_eq!(inverted.len(), 900);
+        assert_eq!(&inverted[..3], [1, 2, 3]);
+        assert_eq!(lines(errors().with_invert(true).with_sampling(Sampling { tail: Some(1), ..Default::default() }).unwrap()), [999]);
+    }
+    
+    #[test]
+    fn test_context_records() {
+        let records: Vec<Value> = [(0, 1, "INFO"), (10, 2, "INFO"), (20, 1, "INFO"), (30, 2, "ERROR"), (40, 1, "INFO"), (100, 2, "INFO"), (110, 2, "INFO")]
+            .iter()
+            .enumerate()
+            .map(|(i, (secs, thread, level))| json!({"line_num": i, "timestamp": format!("2025-06-12T08:{:02}:{:02}.000000Z", secs / 60, secs % 60),
+                "thread": thread, "level": level}))
+            .collect();
+        let fixture = Fixture::with_records(&records);
+        let errors = || SolidFireFilter::new(None, None, None, None, vec![Predicate::parse("level=ERROR").unwrap()], None).unwrap();
+        let kinds = |context: ContextOptions| {
+            let records = fixture.records(&errors().with_context_options(context));
+            records.iter().map(|r| format!("{}{}", r["line_num"], &r["match_kind"].as_str().unwrap()[..1])).collect::<Vec<_>>().join(" ")
+        };
+        
+        assert_eq!(kinds(ContextOptions { before: 2, after: 1, scope: Some(ContextScope::Thread), ..Default::default() }), "1c 3m 5c");
+        
+        let filter = errors().with_context_options(ContextOptions { after: 10, ..Default::default() });
+        let stats = filter.filter_file(&fixture.input, &fixture.output, &OutputOptions::default()).unwrap();
+        assert_eq!((stats.records_matched, stats.records_context, stats.records_written), (1, 3, 4));
+        // Without context, records are written unmarked
+        assert!(!fixture.output(&errors()).contains("match_kind"));
+    }
+    
+    #[test]
+    fn test_atomic_output_on_interrupt() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("input.json");
+        let output_path = temp_dir.path().join("output.json");
+        fs::write(&input_path, r#"[{"date": "2025-06-12", "time": "08:35:00"}, {"date": "2025-06-12", "time": "08:36:00"}]"#).unwrap();
+        fs::write(&output_path, "previous output").unwrap();
+        
+        let filter = SolidFireFilter::new(None, None, None, None, Vec::new(), None).unwrap();
+        let mut options = OutputOptions {
+            keep_partial: false,
+            interrupt: Some(Arc::new(AtomicBool::new(true))),
+        };
+        
+        assert!(filter.filter_file(&input_path, &output_path, &options).unwrap().interrupted);
+        assert_eq!(fs::read_to_string(&output_path).unwrap(), "previous output");
+        assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 2, "temporary file should be removed");
+        
+        options.keep_partial = true;
+        assert!(filter.filter_file(&input_path, &output_path, &options).unwrap().interrupted);
+        let partial: Value = serde_json::from_str(&fs::read_to_string(&output_path).unwrap()).unwrap();
+        assert!(partial.as_array().unwrap().is_empty());
+        
+        options.interrupt = None;
+        filter.filter_file(&input_path, &output_path, &options).unwrap();
+        let complete: Value = serde_json::from_str(&fs::read_to_string(&output_path).unwrap()).unwrap();
+        assert_eq!(complete.as_array().unwrap().len(), 2);
+    }
+    
+    #[test]
+    fn test_run_summary() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("input.json");
+        let output_path = temp_dir.path().join("output.json");
+        let summary_path = temp_dir.path().join("summary.json");
+        fs::write(&input_path, r#"[
+            {"date": "2025-06-12", "time": "08:40:00", "timestamp": "2025-06-12T08:40:00.000000Z", "serviceID": 230},
+            {"date": "2025-06-12", "time": "08:35:00", "timestamp": "2025-06-12T08:35:00.000000Z", "serviceID": 230},
+            {"date": "2025-06-12", "time": "08:30:00", "timestamp": "2025-06-12T08:30:00.000000Z", "serviceID": 110}
+        ]"#).unwrap();
+        
+        let filter = SolidFireFilter::new(None, None, None, None, vec![Predicate::parse("serviceID=230").unwrap()], Some("info")).unwrap();
+        let stats = filter.filter_file(&input_path, &output_path, &OutputOptions::default()).unwrap();
+        RunSummary::new(&input_path, &output_path, filter.criteria(), RunStatus::Ok)
+            .with_stats(&stats)
+            .write(&summary_path)
+            .unwrap();
+        
+        let summary: Value = serde_json::from_str(&fs::read_to_string(&summary_path).unwrap()).unwrap();
+        assert_eq!(summary["exit_code"], 0);
+        assert_eq!(summary["records_in"], 3);
+        assert_eq!(summary["records_matched"], 0, "records without a level fail --min-level");
+        assert_eq!(summary["criteria"]["min_level"], "info");
+        assert_eq!(summary["criteria"]["fields"][0], json!({"field": "serviceID", "op": "=", "value": "230"}));
+        assert_eq!(summary["input"]["fingerprint_len"], fs::metadata(&input_path).unwrap().len());
+        
+        let filter = SolidFireFilter::new(None, None, None, None, vec![Predicate::parse("serviceID=230").unwrap()], None).unwrap();
+        let stats = filter.filter_file(&input_path, &output_path, &OutputOptions::default()).unwrap();
+        assert_eq!(stats.records_written, 2);
+        assert_eq!(stats.time_span.first.as_deref(), Some("2025-06-12T08:35:00.000000Z"));
+        assert_eq!(stats.time_span.last.as_deref(), Some("2025-06-12T08:40:00.000000Z"));
+    }
+    
 }
\ No newline at end of file
@@ -0,0 +1,123 @@
+use anyhow::{Context, Result};
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// `-` stands for stdin as an input path and stdout as an output path.
+pub fn is_stdio(path: &Path) -> bool {
+    path == Path::new("-")
+}
+
+/// True if the error comes from writing to a pipe whose reader has gone
+/// away (e.g. `| head`), which a pipeline stage should treat as a clean exit.
+pub fn is_broken_pipe(err: &anyhow::Error) -> bool {
+    err.chain().any(|cause| {
+        cause
+            .downcast_ref::<io::Error>()
+            .is_some_and(|e| e.kind() == io::ErrorKind::BrokenPipe)
+    })
+}
+
+/// A file written under a temporary name next to its destination and
+/// renamed into place by `commit`. If it is dropped without being committed
+/// the temporary file is removed and the destination is left untouched, so
+/// an error or Ctrl-C never leaves a truncated JSON array behind.
+pub struct AtomicFile {
+    file: File,
+    tmp_path: PathBuf,
+    final_path: PathBuf,
+    committed: bool,
+}
+
+impl AtomicFile {
+    pub fn create(path: &Path) -> Result<Self> {
+        let file_name = path
+            .file_name()
+            .with_context(|| format!("Output path has no file name: {}", path.display()))?;
+        let mut tmp_name = std::ffi::OsString::from(".");
+        tmp_name.push(file_name);
+        tmp_name.push(format!(".tmp-{}", std::process::id()));
+        let tmp_path = path.with_file_name(tmp_name);
+
+        let file = File::create(&tmp_path)
+            .with_context(|| format!("Failed to create output file: {}", tmp_path.display()))?;
+        Ok(AtomicFile {
+            file,
+            tmp_path,
+            final_path: path.to_path_buf(),
+            committed: false,
+        })
+    }
+
+    /// Sync the data to disk and atomically replace the destination.
+    pub fn commit(mut self) -> Result<()> {
+        self.file.sync_all()?;
+        fs::rename(&self.tmp_path, &self.final_path).with_context(|| {
+            format!("Failed to move output into place: {}", self.final_path.display())
+        })?;
+        self.committed = true;
+        Ok(())
+    }
+}
+
+impl Write for AtomicFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+impl Drop for AtomicFile {
+    fn drop(&mut self) {
+        if !self.committed {
+            let _ = fs::remove_file(&self.tmp_path);
+        }
+    }
+}
+
+/// Destination for filtered output: an atomically replaced file, or stdout.
+pub enum OutputTarget {
+    Atomic(AtomicFile),
+    Stdout(io::Stdout),
+}
+
+impl OutputTarget {
+    /// Open `path` for writing, or stdout when `path` is `-`.
+    pub fn create(path: &Path) -> Result<Self> {
+        if is_stdio(path) {
+            Ok(OutputTarget::Stdout(io::stdout()))
+        } else {
+            Ok(OutputTarget::Atomic(AtomicFile::create(path)?))
+        }
+    }
+
+    /// Move a file into place, or flush stdout.
+    pub fn commit(self) -> Result<()> {
+        match self {
+            OutputTarget::Atomic(file) => file.commit(),
+            OutputTarget::Stdout(mut stdout) => {
+                stdout.flush()?;
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Write for OutputTarget {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            OutputTarget::Atomic(file) => file.write(buf),
+            OutputTarget::Stdout(stdout) => stdout.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            OutputTarget::Atomic(file) => file.flush(),
+            OutputTarget::Stdout(stdout) => stdout.flush(),
+        }
+    }
+}
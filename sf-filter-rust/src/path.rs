@@ -0,0 +1,92 @@
+use anyhow::Result;
+use serde_json::Value;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Key(String),
+    Index(usize),
+    /// `[*]`: every element of an array
+    Each,
+}
+
+/// A field reference such as `serviceID`, `clusterFault.severity`,
+/// `params.volumes[*]` or `params.drives[0].id`.
+///
+/// sf-parser's own records are flat (a nested `clusterFault={...}` is kept
+/// as a `complex_clusterFault` string), so paths are for other JSON input.
+/// A top-level key spelled exactly like the path wins, for input whose
+/// nested values were already flattened into dotted keys; otherwise the
+/// path walks nested objects and arrays. `[*]` fans out over an array, so
+/// one path can reach several values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldPath {
+    text: String,
+    segments: Vec<Segment>,
+}
+
+impl FieldPath {
+    pub fn parse(text: &str) -> Result<FieldPath> {
+        let invalid = |reason: &str| anyhow::anyhow!("Invalid field path `{}`: {}", text, reason);
+        let mut segments = Vec::new();
+        for part in text.split('.') {
+            let (key, mut rest) = part.split_at(part.find('[').unwrap_or(part.len()));
+            if key.is_empty() {
+                return Err(invalid("empty field name"));
+            }
+            segments.push(Segment::Key(key.to_string()));
+            while !rest.is_empty() {
+                let close = rest.find(']').ok_or_else(|| invalid("missing `]`"))?;
+                let segment = match &rest[1..close] {
+                    "*" => Segment::Each,
+                    index => Segment::Index(index.parse().map_err(|_| invalid("expected [*] or an index like [0]"))?),
+                };
+                segments.push(segment);
+                rest = &rest[close + 1..];
+                if !rest.is_empty() && !rest.starts_with('[') {
+                    return Err(invalid("expected `.` or `[` after `]`"));
+                }
+            }
+        }
+        Ok(FieldPath {
+            text: text.to_string(),
+            segments,
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    /// Whether `f` holds for any value the path reaches in `record`. An
+    /// absent path reaches nothing, so this is false.
+    pub fn any(&self, record: &Value, mut f: impl FnMut(&Value) -> bool) -> bool {
+        if let Some(value) = record.get(&self.text) {
+            return f(value);
+        }
+        walk(record, &self.segments, &mut f)
+    }
+
+    /// Whether the path reaches a value other than null.
+    pub fn exists(&self, record: &Value) -> bool {
+        self.any(record, |value| !value.is_null())
+    }
+}
+
+fn walk(value: &Value, segments: &[Segment], f: &mut impl FnMut(&Value) -> bool) -> bool {
+    let Some((segment, rest)) = segments.split_first() else {
+        return f(value);
+    };
+    match (segment, value) {
+        (Segment::Key(key), Value::Object(fields)) => fields.get(key).is_some_and(|v| walk(v, rest, f)),
+        (Segment::Index(i), Value::Array(items)) => items.get(*i).is_some_and(|v| walk(v, rest, f)),
+        (Segment::Each, Value::Array(items)) => items.iter().any(|v| walk(v, rest, f)),
+        _ => false,
+    }
+}
+
+impl fmt::Display for FieldPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
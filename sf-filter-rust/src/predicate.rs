@@ -0,0 +1,477 @@
+use aho_corasick::{AhoCorasick, AhoCorasickBuilder};
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde_json::Value;
+use std::cmp::Ordering;
+use std::fmt;
+
+use crate::path::FieldPath;
+
+/// Comparison operators, longest spelling first so `<=` isn't read as `<`.
+const OPERATORS: &[(&str, CompareOp)] = &[
+    ("=~", CompareOp::Matches),
+    ("!~", CompareOp::NotMatches),
+    ("!=", CompareOp::Ne),
+    ("<=", CompareOp::Le),
+    (">=", CompareOp::Ge),
+    ("==", CompareOp::Eq),
+    ("=", CompareOp::Eq),
+    ("<", CompareOp::Lt),
+    (">", CompareOp::Gt),
+];
+
+/// Operators written as words, after whitespace: `volumeID in 1207,1210`.
+const WORD_OPERATORS: &[(&str, CompareOp)] = &[
+    ("not in ", CompareOp::NotIn),
+    ("in ", CompareOp::In),
+    ("contains ", CompareOp::Contains),
+    ("like ", CompareOp::Like),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    In,
+    NotIn,
+    /// Regex search
+    Matches,
+    NotMatches,
+    /// Case-insensitive substring search for any of the values
+    Contains,
+    /// Glob over the whole value
+    Like,
+    /// The field is present and not null; takes no values
+    Exists,
+    Missing,
+}
+
+impl CompareOp {
+    pub fn symbol(self) -> &'static str {
+        match self {
+            CompareOp::Eq => "=",
+            CompareOp::Ne => "!=",
+            CompareOp::Lt => "<",
+            CompareOp::Le => "<=",
+            CompareOp::Gt => ">",
+            CompareOp::Ge => ">=",
+            CompareOp::In => "in",
+            CompareOp::NotIn => "not in",
+            CompareOp::Matches => "=~",
+            CompareOp::NotMatches => "!~",
+            CompareOp::Contains => "contains",
+            CompareOp::Like => "like",
+            CompareOp::Exists => "exists",
+            CompareOp::Missing => "missing",
+        }
+    }
+
+    /// Operators that only match string values (`contains` also matches
+    /// arrays holding one of the values).
+    pub fn is_text(self) -> bool {
+        matches!(self, CompareOp::Matches | CompareOp::NotMatches | CompareOp::Contains | CompareOp::Like)
+    }
+
+    /// `exists(field)` and `missing(field)`, which compare against nothing.
+    pub fn is_existence(self) -> bool {
+        matches!(self, CompareOp::Exists | CompareOp::Missing)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum LiteralKind {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str,
+}
+
+/// A value to compare against, typed from how it was written: `null`,
+/// `true`/`false`, an integer, a float (`1e12`) or a string. The source text
+/// is kept so string fields compare against exactly what was typed
+/// (`serial=007` doesn't become `7`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Literal {
+    pub text: String,
+    kind: LiteralKind,
+}
+
+impl Literal {
+    pub fn parse(text: &str) -> Literal {
+        let text = text.trim();
+        let quoted = text.len() >= 2 && text.starts_with('"') && text.ends_with('"');
+        if quoted {
+            return Literal::string(&text[1..text.len() - 1]);
+        }
+        let kind = if text == "null" {
+            LiteralKind::Null
+        } else if let Ok(b) = text.parse::<bool>() {
+            LiteralKind::Bool(b)
+        } else if let Ok(i) = text.parse::<i64>() {
+            LiteralKind::Int(i)
+        } else if let Some(f) = text.parse::<f64>().ok().filter(|f| f.is_finite()) {
+            LiteralKind::Float(f)
+        } else {
+            LiteralKind::Str
+        };
+        Literal {
+            text: text.to_string(),
+            kind,
+        }
+    }
+
+    /// A literal that is a string whatever it looks like.
+    pub fn string(text: &str) -> Literal {
+        Literal {
+            text: text.to_string(),
+            kind: LiteralKind::Str,
+        }
+    }
+
+    /// `null`, `boolean`, `number` or `string`.
+    pub fn type_name(&self) -> &'static str {
+        match self.kind {
+            LiteralKind::Null => "null",
+            LiteralKind::Bool(_) => "boolean",
+            LiteralKind::Int(_) | LiteralKind::Float(_) => "number",
+            LiteralKind::Str => "string",
+        }
+    }
+
+    /// Order a record value against this literal, or `None` when the two
+    /// can't be compared (a number against a word, null against a value).
+    ///
+    /// Integers compare exactly, so IDs beyond 2^53 don't collide the way
+    /// they would through `f64`. String fields compare as text, except
+    /// against a number: then the string is read as one (`"999" < 1000`),
+    /// and one that isn't a number doesn't compare.
+    pub fn compare(&self, value: &Value) -> Option<Ordering> {
+        match (value, &self.kind) {
+            (Value::Null, LiteralKind::Null) => Some(Ordering::Equal),
+            (Value::Null, _) | (_, LiteralKind::Null) => None,
+            (Value::Bool(a), LiteralKind::Bool(b)) => Some(a.cmp(b)),
+            (Value::Number(n), LiteralKind::Int(i)) => {
+                if let Some(v) = n.as_i64() {
+                    Some(v.cmp(i))
+                } else if n.is_u64() {
+                    // Larger than any i64
+                    Some(Ordering::Greater)
+                } else {
+                    n.as_f64()?.partial_cmp(&(*i as f64))
+                }
+            }
+            (Value::Number(n), LiteralKind::Float(f)) => n.as_f64()?.partial_cmp(f),
+            (Value::String(s), LiteralKind::Int(_) | LiteralKind::Float(_)) => self.compare(&parse_number(s)?),
+            (Value::String(s), _) => Some(s.as_str().cmp(self.text.as_str())),
+            _ => None,
+        }
+    }
+
+    /// Whether a record value equals this literal. A string field must
+    /// match the literal as written, so `serial=007` doesn't match `"7"`.
+    pub fn equals(&self, value: &Value) -> bool {
+        match (value, &self.kind) {
+            (Value::String(s), LiteralKind::Int(_) | LiteralKind::Float(_)) => *s == self.text,
+            _ => self.compare(value) == Some(Ordering::Equal),
+        }
+    }
+}
+
+/// A string field's value as a JSON number, if it is one.
+fn parse_number(text: &str) -> Option<Value> {
+    let text = text.trim();
+    if let Ok(i) = text.parse::<i64>() {
+        Some(Value::from(i))
+    } else if let Ok(u) = text.parse::<u64>() {
+        Some(Value::from(u))
+    } else {
+        serde_json::Number::from_f64(text.parse().ok()?).map(Value::Number)
+    }
+}
+
+impl fmt::Display for Literal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+/// Compiled form of a text operator's pattern, built once per predicate.
+#[derive(Debug, Clone)]
+enum TextMatcher {
+    Regex(Regex),
+    /// Case-insensitive search for any of several literals in one scan
+    Substrings(AhoCorasick),
+}
+
+impl TextMatcher {
+    fn build(op: CompareOp, values: &[Literal]) -> Result<Option<TextMatcher>> {
+        let matcher = match op {
+            CompareOp::Matches | CompareOp::NotMatches => {
+                let pattern = &values[0].text;
+                TextMatcher::Regex(Regex::new(pattern).with_context(|| format!("Invalid regex `{}`", pattern))?)
+            }
+            CompareOp::Like => {
+                let pattern = &values[0].text;
+                TextMatcher::Regex(Regex::new(&glob_to_regex(pattern)).with_context(|| format!("Invalid glob `{}`", pattern))?)
+            }
+            CompareOp::Contains => TextMatcher::Substrings(
+                AhoCorasickBuilder::new()
+                    .ascii_case_insensitive(true)
+                    .build(values.iter().map(|v| v.text.as_str()))
+                    .context("Failed to build substring matcher")?,
+            ),
+            _ => return Ok(None),
+        };
+        Ok(Some(matcher))
+    }
+
+    fn is_match(&self, text: &str) -> bool {
+        match self {
+            TextMatcher::Regex(regex) => regex.is_match(text),
+            TextMatcher::Substrings(substrings) => substrings.is_match(text),
+        }
+    }
+}
+
+/// Translate a glob (`*` any run, `?` one character, `[...]` a class,
+/// `[!...]` a negated one) into an anchored regex.
+pub fn glob_to_regex(glob: &str) -> String {
+    let mut regex = String::from("(?s)^");
+    let mut class_start = None;
+    for (i, c) in glob.char_indices() {
+        match (c, class_start) {
+            ('*', None) => regex.push_str(".*"),
+            ('?', None) => regex.push('.'),
+            ('[', None) => {
+                class_start = Some(i + 1);
+                regex.push('[');
+            }
+            (']', Some(start)) if i > start => {
+                class_start = None;
+                regex.push(']');
+            }
+            ('!' | '^', Some(start)) if i == start => regex.push('^'),
+            ('-', Some(_)) => regex.push('-'),
+            (c, _) => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push('$');
+    regex
+}
+
+/// One field condition, e.g. `totalMS>1000`, `volumeID in 1207,1210` or
+/// `exists(clusterFault.severity)`.
+///
+/// `!=`, `not in` and `!~` are the exact negation of `=`, `in` and `=~`, so
+/// a record whose field is null or absent passes them, as in Nushell's
+/// `where`. Ordering comparisons never match null or absent fields, and the
+/// text operators only match string values. A path reaching several values
+/// (`params.volumes[*]`) matches when any of them does, so its negations
+/// mean none does. `contains` on an array checks membership.
+#[derive(Debug, Clone)]
+pub struct Predicate {
+    pub field: String,
+    pub op: CompareOp,
+    pub values: Vec<Literal>,
+    path: FieldPath,
+    matcher: Option<TextMatcher>,
+}
+
+impl Predicate {
+    /// Build a predicate, compiling the pattern of a text operator.
+    pub fn new(field: &str, op: CompareOp, values: Vec<Literal>) -> Result<Predicate> {
+        let path = FieldPath::parse(field)?;
+        let matcher = TextMatcher::build(op, &values)?;
+        Ok(Predicate {
+            field: field.to_string(),
+            op,
+            values,
+            path,
+            matcher,
+        })
+    }
+
+    /// `exists(field)` or `missing(field)`.
+    pub fn existence(field: &str, op: CompareOp) -> Result<Predicate> {
+        debug_assert!(op.is_existence());
+        Predicate::new(field, op, Vec::new())
+    }
+
+    /// Parse the `--field` syntax: `field<op>value` with one of
+    /// `= == != < <= > >= =~ !~`, or a word operator after a space:
+    /// `field in a,b,c`, `field not in a,b,c`, `field contains a,b,c` or
+    /// `field like glob`; or `exists(field)` / `missing(field)`.
+    pub fn parse(spec: &str) -> Result<Predicate> {
+        let invalid = || {
+            anyhow::anyhow!(
+                "Invalid field filter: {} (use field=value, field>value, ... or field in a,b)",
+                spec
+            )
+        };
+
+        let spec = spec.trim();
+        for op in [CompareOp::Exists, CompareOp::Missing] {
+            let field = spec
+                .strip_prefix(op.symbol())
+                .and_then(|rest| rest.trim_start().strip_prefix('('))
+                .and_then(|rest| rest.strip_suffix(')'));
+            if let Some(field) = field {
+                return Predicate::existence(field.trim(), op);
+            }
+        }
+
+        let word_op = spec
+            .split_once(char::is_whitespace)
+            .filter(|(field, _)| !field.contains(|c| "=!<>".contains(c)))
+            .and_then(|(field, rest)| {
+                let rest = rest.trim_start();
+                WORD_OPERATORS
+                    .iter()
+                    .find_map(|(word, op)| rest.strip_prefix(word).map(|value| (field, *op, value)))
+            });
+        if let Some((field, op, value)) = word_op {
+            let values: Vec<Literal> = match op {
+                CompareOp::Like => vec![Literal::parse(value)],
+                _ => value.split(',').map(Literal::parse).collect(),
+            };
+            if values.iter().any(|v| v.text.is_empty()) {
+                return Err(invalid());
+            }
+            return Predicate::new(field, op, values);
+        }
+
+        let start = spec.find(|c| "=!<>".contains(c)).ok_or_else(invalid)?;
+        let (field, rest) = spec.split_at(start);
+        let (symbol, op) = OPERATORS
+            .iter()
+            .find(|(symbol, _)| rest.starts_with(symbol))
+            .ok_or_else(invalid)?;
+        let field = field.trim();
+        if field.is_empty() {
+            return Err(invalid());
+        }
+        Predicate::new(field, *op, vec![Literal::parse(&rest[symbol.len()..])])
+    }
+
+    /// The field as a path into the record.
+    pub fn path(&self) -> &FieldPath {
+        &self.path
+    }
+
+    pub fn matches(&self, record: &Value) -> bool {
+        let any = |f: &dyn Fn(&Value) -> bool| self.path.any(record, f);
+        let equals = |value: &Value| {
+            self.values.iter().any(|literal| literal.equals(value))
+        };
+        let ordered = |accept: fn(Ordering) -> bool| any(&|value| self.values[0].compare(value).is_some_and(accept));
+        let text_matches = |value: &Value| match (value, &self.matcher) {
+            (Value::String(text), Some(matcher)) => matcher.is_match(text),
+            _ => false,
+        };
+
+        match self.op {
+            CompareOp::Eq | CompareOp::In => any(&equals),
+            CompareOp::Ne | CompareOp::NotIn => !any(&equals),
+            CompareOp::Lt => ordered(Ordering::is_lt),
+            CompareOp::Le => ordered(Ordering::is_le),
+            CompareOp::Gt => ordered(Ordering::is_gt),
+            CompareOp::Ge => ordered(Ordering::is_ge),
+            CompareOp::Matches | CompareOp::Like => any(&text_matches),
+            CompareOp::NotMatches => !any(&text_matches),
+            CompareOp::Contains => any(&|value| match value {
+                Value::Array(items) => items.iter().any(equals),
+                _ => text_matches(value),
+            }),
+            CompareOp::Exists => self.path.exists(record),
+            CompareOp::Missing => !self.path.exists(record),
+        }
+    }
+
+    /// The right-hand side as written, e.g. `1207,1210`.
+    pub fn value_text(&self) -> String {
+        self.values.iter().map(|v| v.text.as_str()).collect::<Vec<_>>().join(",")
+    }
+}
+
+impl fmt::Display for Predicate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.op.is_existence() {
+            return write!(f, "{}({})", self.op.symbol(), self.field);
+        }
+        write!(f, "{} {} {}", self.field, self.op.symbol(), self.value_text())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_field_predicates() {
+        let record = json!({"serviceID": 230, "totalMS": 1500.5, "usedBytes": 1909106990888_i64, "volumeID": 1210,
+            "snapshotID": 9007199254740993_i64, "level": "ERROR", "serial": "007", "flag": true, "sliceID": null});
+        let matches = |spec: &str| Predicate::parse(spec).unwrap().matches(&record);
+
+        assert!(matches("serviceID=230") && matches("serviceID == 230") && !matches("serviceID=231"));
+        assert!(matches("serviceID!=110") && !matches("serviceID!=230"));
+        assert!(matches("totalMS>1000") && matches("totalMS<=1500.5") && !matches("totalMS<1000"));
+        assert!(matches("usedBytes>=1e12") && !matches("usedBytes>2e12"));
+        assert!(matches("volumeID in 1207,1210") && !matches("volumeID not in 1207, 1210"));
+        assert!(matches("level in WARN,ERROR") && matches("serial=007") && matches("flag=true"));
+        // IDs compare exactly, not through f64 (2^53 + 1 would equal 2^53)
+        assert!(!matches("snapshotID=9007199254740992") && matches("snapshotID>9007199254740992"));
+        // Null and absent fields: only = null matches; != and not in pass them
+        assert!(matches("sliceID=null") && !matches("sliceID>0") && matches("sliceID!=5"));
+        assert!(!matches("missing=1") && matches("missing!=1") && !matches("missing<1"));
+        assert!(!matches("serviceID=abc") && !matches("serviceID<abc"));
+        // String fields order numerically against a number, not as text
+        let record = json!({"totalMS": "999", "serial": "007", "level": "ERROR"});
+        let matches = |spec: &str| Predicate::parse(spec).unwrap().matches(&record);
+        assert!(!matches("totalMS>1000") && matches("totalMS<1000") && matches("totalMS>=999.0"));
+        assert!(matches("serial>5") && matches("serial=007") && !matches("serial=7"));
+        assert!(!matches("level>5") && !matches("level<5"));
+
+        assert_eq!(Predicate::parse("volumeID not in 1207,1210").unwrap().op, CompareOp::NotIn);
+        assert!(Predicate::parse("serviceID").is_err());
+        assert!(Predicate::parse("=230").is_err());
+        assert!(Predicate::parse("volumeID in 1207,").is_err());
+    }
+
+    #[test]
+    fn test_text_operators() {
+        let record = json!({"content": "Deleting empty group snapshot groupSnapshotID=13933489", "class": "SliceSnapshots",
+            "raw_line": "... [Snaps] ...", "serviceID": 230, "hostname": null});
+        let matches = |spec: &str| Predicate::parse(spec).unwrap().matches(&record);
+
+        assert!(matches("content=~Delet(e|ing)") && !matches("content=~^delete") && matches("content!~purge"));
+        assert!(matches("content contains DELETING") && matches("content contains purge,Group Snap"));
+        assert!(!matches("content contains purge,expire"));
+        assert!(matches("class like Slice*") && matches("class like Slice?napshot[sS]") && !matches("class like Snap*"));
+        assert!(matches("raw_line like *[[]Snaps]*"));
+        // Only text matches; !~ is the negation, so non-text passes it
+        assert!(!matches("serviceID=~230") && matches("serviceID!~230") && !matches("hostname contains a"));
+        assert!(Predicate::parse("content=~delet(").is_err());
+    }
+
+    #[test]
+    fn test_paths_and_existence() {
+        let record = json!({"clusterFault.severity": "Critical", "params": {"volumes": ["1207", "1210"], "drives": [{"id": 73, "slot": 4}]},
+            "driveIDs": [71, 73], "serviceID": null, "method": "ListVolumes"});
+        let matches = |spec: &str| Predicate::parse(spec).unwrap().matches(&record);
+
+        assert!(matches("clusterFault.severity=Critical") && matches("params.drives[0].slot>3") && !matches("params.drives[1].slot>3"));
+        assert!(matches(r#"params.volumes[*]="1210""#) && !matches("params.volumes[*]=1211") && matches("params.volumes[*]!=1211"));
+        assert!(matches("params.drives[*].id in 72,73") && matches("params.volumes[*] like 12*"));
+        assert!(matches("driveIDs contains 73") && !matches("driveIDs contains 7") && matches("method contains volume"));
+        // exists/missing differ from == null: null is missing, but only an absent field fails == null
+        assert!(matches("exists(params.volumes)") && matches("missing(serviceID)") && matches("missing(sliceID)"));
+        assert!(!matches("exists(serviceID)") && matches("serviceID=null") && !matches("sliceID=null"));
+        assert!(Predicate::parse("params..volumes=1").is_err() && Predicate::parse("params.volumes[x]=1").is_err());
+    }
+}
@@ -0,0 +1,234 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// Preset file read when `--presets` isn't given, if it exists.
+pub const DEFAULT_PRESETS_FILE: &str = "sf-filter-presets.toml";
+
+/// A named filter recipe: any mix of the command-line criteria, written
+/// with the flag names in snake_case. String values may use `${name}`
+/// placeholders, filled from `--param name=value`, the preset's `params`
+/// defaults, or the built-in `${today}` (local date).
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Preset {
+    pub description: Option<String>,
+    /// Default parameter values
+    pub params: BTreeMap<String, String>,
+    pub start_date: Option<String>,
+    pub end_date: Option<String>,
+    pub start_time: Option<String>,
+    pub end_time: Option<String>,
+    pub since: Option<String>,
+    pub until: Option<String>,
+    pub around: Option<String>,
+    pub window: Option<String>,
+    pub field: Vec<String>,
+    #[serde(rename = "where")]
+    pub where_expr: Option<String>,
+    pub min_level: Option<String>,
+    pub before: Option<usize>,
+    pub after: Option<usize>,
+    pub context_time: Option<String>,
+    pub context_by: Option<String>,
+    pub select: Vec<String>,
+    pub exclude: Vec<String>,
+    pub prune_empty_columns: Option<bool>,
+    pub output: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PresetsFile {
+    #[serde(default)]
+    preset: BTreeMap<String, Preset>,
+}
+
+/// The presets of one file, by name.
+#[derive(Debug, Clone, Default)]
+pub struct Presets {
+    presets: BTreeMap<String, Preset>,
+}
+
+impl Presets {
+    /// Load presets from a TOML file:
+    ///
+    /// ```toml
+    /// [preset.service]
+    /// description = "Warnings and errors from one service"
+    /// params = { id = "230" }
+    /// where = 'serviceID == ${id}'
+    /// min_level = "warn"
+    /// ```
+    pub fn load(path: &Path) -> Result<Presets> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read presets: {}", path.display()))?;
+        Presets::parse(&text).with_context(|| format!("Invalid presets: {}", path.display()))
+    }
+
+    pub fn parse(text: &str) -> Result<Presets> {
+        let file: PresetsFile = toml::from_str(text)?;
+        Ok(Presets { presets: file.preset })
+    }
+
+    /// Names and descriptions, sorted by name.
+    pub fn list(&self) -> impl Iterator<Item = (&str, Option<&str>)> {
+        self.presets.iter().map(|(name, preset)| (name.as_str(), preset.description.as_deref()))
+    }
+
+    /// The preset `name` with its placeholders filled in from `params`.
+    pub fn resolve(&self, name: &str, params: &[(String, String)]) -> Result<Preset> {
+        let mut presets = self.resolve_all(&[name], params)?;
+        Ok(presets.remove(0))
+    }
+
+    /// Several presets sharing one set of `params`; each parameter must be
+    /// used by at least one of them.
+    pub fn resolve_all(&self, names: &[&str], params: &[(String, String)]) -> Result<Vec<Preset>> {
+        let presets = names
+            .iter()
+            .map(|name| self.get(name).map(|preset| (*name, preset)))
+            .collect::<Result<Vec<_>>>()?;
+        for (key, _) in params {
+            if !presets.iter().any(|(_, preset)| preset.params.contains_key(key) || preset.uses(key)) {
+                let names: Vec<String> = names.iter().map(|name| format!("`{}`", name)).collect();
+                anyhow::bail!("Preset {} has no parameter `{}`", names.join(", "), key);
+            }
+        }
+        presets.into_iter().map(|(name, preset)| preset.bind(name, params)).collect()
+    }
+
+    fn get(&self, name: &str) -> Result<&Preset> {
+        self.presets.get(name).with_context(|| {
+            let names: Vec<&str> = self.presets.keys().map(String::as_str).collect();
+            if names.is_empty() {
+                format!("Unknown preset `{}` (no presets defined)", name)
+            } else {
+                format!("Unknown preset `{}` (available: {})", name, names.join(", "))
+            }
+        })
+    }
+}
+
+impl Preset {
+    fn bind(&self, name: &str, params: &[(String, String)]) -> Result<Preset> {
+        let mut values: BTreeMap<&str, String> = BTreeMap::new();
+        values.insert("today", chrono::Local::now().date_naive().to_string());
+        for (key, value) in &self.params {
+            values.insert(key, value.clone());
+        }
+        for (key, value) in params {
+            values.insert(key, value.clone());
+        }
+
+        let fill = |text: &String| -> Result<String> {
+            substitute(text, &values).with_context(|| format!("Preset `{}`", name))
+        };
+        let fill_opt = |text: &Option<String>| text.as_ref().map(fill).transpose();
+        Ok(Preset {
+            description: self.description.clone(),
+            params: self.params.clone(),
+            start_date: fill_opt(&self.start_date)?,
+            end_date: fill_opt(&self.end_date)?,
+            start_time: fill_opt(&self.start_time)?,
+            end_time: fill_opt(&self.end_time)?,
+            since: fill_opt(&self.since)?,
+            until: fill_opt(&self.until)?,
+            around: fill_opt(&self.around)?,
+            window: fill_opt(&self.window)?,
+            field: self.field.iter().map(fill).collect::<Result<_>>()?,
+            where_expr: fill_opt(&self.where_expr)?,
+            min_level: fill_opt(&self.min_level)?,
+            before: self.before,
+            after: self.after,
+            context_time: fill_opt(&self.context_time)?,
+            context_by: fill_opt(&self.context_by)?,
+            select: self.select.iter().map(fill).collect::<Result<_>>()?,
+            exclude: self.exclude.iter().map(fill).collect::<Result<_>>()?,
+            prune_empty_columns: self.prune_empty_columns,
+            output: fill_opt(&self.output)?,
+        })
+    }
+
+    /// Whether any string of the preset has a `${key}` placeholder.
+    fn uses(&self, key: &str) -> bool {
+        let placeholder = format!("${{{}}}", key);
+        [
+            &self.start_date, &self.end_date, &self.start_time, &self.end_time, &self.since, &self.until,
+            &self.around, &self.window, &self.where_expr, &self.min_level, &self.context_time,
+            &self.context_by, &self.output,
+        ]
+        .into_iter()
+        .flatten()
+        .chain(&self.field)
+        .chain(&self.select)
+        .chain(&self.exclude)
+        .any(|text| text.contains(&placeholder))
+    }
+}
+
+/// Replace each `${name}` in `text` with its value.
+fn substitute(text: &str, values: &BTreeMap<&str, String>) -> Result<String> {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("${") {
+        out.push_str(&rest[..start]);
+        let end = rest[start..].find('}').with_context(|| format!("Unclosed `${{` in `{}`", text))?;
+        let key = &rest[start + 2..start + end];
+        let value = values
+            .get(key)
+            .with_context(|| format!("Missing parameter: pass --param {}=...", key))?;
+        out.push_str(value);
+        rest = &rest[start + end + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve() {
+        let presets = Presets::parse(r#"
+            [preset.snapshots]
+            description = "Snapshot-related logs"
+            field = ["component=Snaps"]
+            output = "data/snapshots-only.json"
+
+            [preset.service]
+            params = { level = "warn" }
+            field = ["serviceID=${id}"]
+            where = 'exists(serviceID)'
+            min_level = "${level}"
+            since = "${day}T00:00:00Z"
+            before = 3
+
+            [preset.today]
+            start_date = "${today}"
+            end_date = "${today}"
+        "#).unwrap();
+        assert_eq!(presets.list().map(|(name, _)| name).collect::<Vec<_>>(), ["service", "snapshots", "today"]);
+        assert_eq!(presets.resolve("snapshots", &[]).unwrap().output.as_deref(), Some("data/snapshots-only.json"));
+
+        let param = |spec: &str| {
+            let (key, value) = spec.split_once('=').unwrap();
+            (key.to_string(), value.to_string())
+        };
+        let service = presets.resolve("service", &[param("id=230"), param("day=2025-06-12")]).unwrap();
+        assert_eq!(service.field, ["serviceID=230"]);
+        assert_eq!((service.min_level.as_deref(), service.since.as_deref(), service.before), (Some("warn"), Some("2025-06-12T00:00:00Z"), Some(3)));
+        let service = presets.resolve("service", &[param("id=110"), param("day=2025-06-12"), param("level=error")]).unwrap();
+        assert_eq!(service.min_level.as_deref(), Some("error"));
+        assert_eq!(presets.resolve("today", &[]).unwrap().start_date, Some(chrono::Local::now().date_naive().to_string()));
+
+        let error = |name: &str, params: &[(String, String)]| format!("{:#}", presets.resolve(name, params).unwrap_err());
+        assert_eq!(error("service", &[param("day=2025-06-12")]), "Preset `service`: Missing parameter: pass --param id=...");
+        assert_eq!(error("snapshots", &[param("id=230")]), "Preset `snapshots` has no parameter `id`");
+        assert_eq!(error("snapshot", &[]), "Unknown preset `snapshot` (available: service, snapshots, today)");
+        assert!(Presets::parse("[preset.x]\nstart_dat = \"2025-06-12\"").is_err());
+    }
+}
@@ -0,0 +1,152 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde_json::Value;
+use std::collections::HashSet;
+
+use crate::predicate::glob_to_regex;
+
+/// A column name, or a glob over names (`clusterFault.*`).
+#[derive(Debug, Clone)]
+enum ColumnPattern {
+    Name(String),
+    Glob(Regex),
+}
+
+impl ColumnPattern {
+    fn parse(pattern: &str) -> Result<ColumnPattern> {
+        if pattern.contains(['*', '?', '[']) {
+            let regex = Regex::new(&glob_to_regex(pattern))
+                .with_context(|| format!("Invalid column pattern `{}`", pattern))?;
+            Ok(ColumnPattern::Glob(regex))
+        } else {
+            Ok(ColumnPattern::Name(pattern.to_string()))
+        }
+    }
+
+    fn matches(&self, column: &str) -> bool {
+        match self {
+            ColumnPattern::Name(name) => name == column,
+            ColumnPattern::Glob(regex) => regex.is_match(column),
+        }
+    }
+}
+
+/// Which columns an output keeps (`--select`, `--exclude`,
+/// `--prune-empty-columns`).
+///
+/// Columns are decided once over all the records an output writes, and
+/// every record is written with exactly those columns (null where it lacks
+/// one), so a projected file keeps sf-parser's consistent schema.
+#[derive(Debug, Clone, Default)]
+pub struct Projection {
+    select: Vec<ColumnPattern>,
+    exclude: Vec<ColumnPattern>,
+    pub prune_empty: bool,
+    /// The patterns as given, for the run summary
+    pub select_text: Vec<String>,
+    pub exclude_text: Vec<String>,
+}
+
+impl Projection {
+    pub fn new(select: &[String], exclude: &[String], prune_empty: bool) -> Result<Projection> {
+        let parse = |patterns: &[String]| patterns.iter().map(|p| ColumnPattern::parse(p.trim())).collect::<Result<Vec<_>>>();
+        Ok(Projection {
+            select: parse(select)?,
+            exclude: parse(exclude)?,
+            prune_empty,
+            select_text: select.to_vec(),
+            exclude_text: exclude.to_vec(),
+        })
+    }
+
+    pub fn is_active(&self) -> bool {
+        !self.select.is_empty() || !self.exclude.is_empty() || self.prune_empty
+    }
+
+    /// The columns to write for `records`, in output order: the `select`
+    /// order (a glob's matches in record order), or record order.
+    pub fn columns(&self, records: &[&Value]) -> Vec<String> {
+        let mut seen = HashSet::new();
+        let mut present = Vec::new();
+        for record in records {
+            if let Some(fields) = record.as_object() {
+                for key in fields.keys() {
+                    if seen.insert(key.as_str()) {
+                        present.push(key.as_str());
+                    }
+                }
+            }
+        }
+
+        let mut columns: Vec<String> = if self.select.is_empty() {
+            present.iter().map(|c| c.to_string()).collect()
+        } else {
+            let mut columns = Vec::new();
+            for pattern in &self.select {
+                match pattern {
+                    // A named column is kept even if no record has it
+                    ColumnPattern::Name(name) => columns.push(name.clone()),
+                    ColumnPattern::Glob(_) => {
+                        columns.extend(present.iter().filter(|c| pattern.matches(c)).map(|c| c.to_string()))
+                    }
+                }
+            }
+            let mut selected = HashSet::new();
+            columns.retain(|c| selected.insert(c.clone()));
+            columns
+        };
+
+        columns.retain(|c| !self.exclude.iter().any(|pattern| pattern.matches(c)));
+        if self.prune_empty {
+            columns.retain(|c| records.iter().any(|record| record.get(c).is_some_and(|v| !v.is_null())));
+        }
+        columns
+    }
+
+    /// `record` as a JSON object with exactly `columns`, then `extra`.
+    pub fn to_json(columns: &[String], record: &Value, extra: Option<(&str, &str)>) -> Result<String> {
+        let mut json = String::from("{");
+        for (i, column) in columns.iter().enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+            json.push_str(&serde_json::to_string(column)?);
+            json.push(':');
+            json.push_str(&serde_json::to_string(record.get(column).unwrap_or(&Value::Null))?);
+        }
+        if let Some((key, value)) = extra {
+            if !columns.is_empty() {
+                json.push(',');
+            }
+            json.push_str(&serde_json::to_string(key)?);
+            json.push(':');
+            json.push_str(&serde_json::to_string(value)?);
+        }
+        json.push('}');
+        Ok(json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_columns() {
+        let records = [
+            json!({"timestamp": "t1", "level": "ERROR", "raw_line": "x", "serviceID": 230, "clusterFault.severity": null, "clusterFault.code": null}),
+            json!({"timestamp": "t2", "level": "INFO", "raw_line": "y", "serviceID": null, "clusterFault.severity": "Critical", "clusterFault.code": null}),
+        ];
+        let records: Vec<&Value> = records.iter().collect();
+        let strings = |items: &[&str]| items.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let columns = |select: &[&str], exclude: &[&str], prune_empty: bool| {
+            Projection::new(&strings(select), &strings(exclude), prune_empty).unwrap().columns(&records).join(" ")
+        };
+
+        assert_eq!(columns(&[], &["raw_line", "clusterFault.*"], false), "level serviceID timestamp");
+        assert_eq!(columns(&["clusterFault.*", "level", "sliceID"], &[], false), "clusterFault.code clusterFault.severity level sliceID");
+        assert_eq!(columns(&["clusterFault.*", "level"], &["level"], true), "clusterFault.severity");
+        assert!(Projection::new(&strings(&["[a"]), &[], false).is_err());
+    }
+}
@@ -0,0 +1,110 @@
+/// Which of the matching records to keep, for skimming a dataset:
+/// `--sample`, then `--every-nth`, then `--limit` or `--tail`.
+///
+/// All of it runs on the match flags after the parallel filter, in input
+/// order, and the sample depends only on the seed and each record's
+/// position, so a run is reproducible whatever the thread count.
+#[derive(Debug, Clone, Default)]
+pub struct Sampling {
+    /// Keep the first N matches
+    pub limit: Option<usize>,
+    /// Keep the last N matches
+    pub tail: Option<usize>,
+    /// Keep about this fraction of the matches
+    pub fraction: Option<f64>,
+    pub seed: u64,
+    /// Keep the 1st, (N+1)th, (2N+1)th, ... match
+    pub every_nth: Option<usize>,
+}
+
+impl Sampling {
+    pub fn is_active(&self) -> bool {
+        self.limit.is_some() || self.tail.is_some() || self.fraction.is_some() || self.every_nth.is_some()
+    }
+
+    /// Thin `matched` (one flag per input record) down to the matches kept.
+    pub fn apply(&self, matched: &[bool]) -> Vec<bool> {
+        let mut kept: Vec<bool> = match self.fraction {
+            Some(fraction) => matched
+                .iter()
+                .enumerate()
+                .map(|(i, &m)| m && self.sampled(i, fraction))
+                .collect(),
+            None => matched.to_vec(),
+        };
+
+        if let Some(n) = self.every_nth {
+            for (count, keep) in kept.iter_mut().filter(|keep| **keep).enumerate() {
+                *keep = count % n == 0;
+            }
+        }
+        if let Some(limit) = self.limit {
+            kept.iter_mut().filter(|keep| **keep).skip(limit).for_each(|keep| *keep = false);
+        }
+        if let Some(tail) = self.tail {
+            kept.iter_mut().rev().filter(|keep| **keep).skip(tail).for_each(|keep| *keep = false);
+        }
+        kept
+    }
+
+    /// Whether record `index` falls in the sample: a hash of the seed and
+    /// index, scaled to [0, 1), against the fraction.
+    fn sampled(&self, index: usize, fraction: f64) -> bool {
+        let hash = splitmix64(self.seed ^ splitmix64(index as u64));
+        let unit = (hash >> 11) as f64 / (1u64 << 53) as f64;
+        unit < fraction
+    }
+
+    /// Human-readable description for the status output.
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(fraction) = self.fraction {
+            parts.push(format!("sample {} (seed {})", fraction, self.seed));
+        }
+        if let Some(n) = self.every_nth {
+            parts.push(format!("every {}th", n));
+        }
+        if let Some(limit) = self.limit {
+            parts.push(format!("first {}", limit));
+        }
+        if let Some(tail) = self.tail {
+            parts.push(format!("last {}", tail));
+        }
+        parts.join(", ")
+    }
+}
+
+/// SplitMix64 finaliser: a fast, well-mixed 64-bit hash.
+fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply() {
+        // Every 10th of 1000 records matches
+        let matched: Vec<bool> = (0..1000).map(|i| i % 10 == 0).collect();
+        let kept = |sampling: Sampling| {
+            sampling.apply(&matched).iter().enumerate().filter(|(_, k)| **k).map(|(i, _)| i).collect::<Vec<_>>()
+        };
+
+        assert_eq!(kept(Sampling { limit: Some(3), ..Default::default() }), [0, 10, 20]);
+        assert_eq!(kept(Sampling { tail: Some(2), ..Default::default() }), [980, 990]);
+        assert_eq!(kept(Sampling { every_nth: Some(25), ..Default::default() }), [0, 250, 500, 750]);
+        assert_eq!(kept(Sampling { every_nth: Some(25), limit: Some(2), ..Default::default() }), [0, 250]);
+
+        // Same seed, same sample, in input order; another seed picks differently
+        let sample = |seed: u64| kept(Sampling { fraction: Some(0.3), seed, ..Default::default() });
+        let first = sample(7);
+        assert!(first.len() > 15 && first.len() < 45, "{}", first.len());
+        assert!(first.windows(2).all(|w| w[0] < w[1]) && first.iter().all(|n| n % 10 == 0));
+        assert_eq!(first, sample(7));
+        assert_ne!(first, sample(8));
+    }
+}
@@ -0,0 +1,49 @@
+use serde_json::Value;
+
+/// Severity names in ascending order; the index is the `severity_num`
+/// emitted by sf-parser (debug = 0 ... emergency = 7).
+pub const SEVERITY_NAMES: [&str; 8] = [
+    "debug", "info", "notice", "warn", "error", "critical", "alert", "emergency",
+];
+
+/// Rank a level tag or severity name on sf-parser's severity scale.
+///
+/// Accepts normalized names (`warn`, `error`), common aliases (`warning`,
+/// `err`, `crit`) and SolidFire's raw `APP-n` syslog tags.
+pub fn severity_rank(level: &str) -> Option<u8> {
+    let level = level.trim();
+    if let Some(priority) = level
+        .strip_prefix("APP-")
+        .or_else(|| level.strip_prefix("app-"))
+    {
+        return match priority.parse::<u8>() {
+            Ok(p) if p <= 7 => Some(7 - p),
+            _ => None,
+        };
+    }
+
+    let rank = match level.to_ascii_lowercase().as_str() {
+        "debug" | "trace" => 0,
+        "info" => 1,
+        "notice" => 2,
+        "warn" | "warning" => 3,
+        "err" | "error" => 4,
+        "crit" | "critical" => 5,
+        "alert" => 6,
+        "emerg" | "emergency" | "fatal" | "panic" => 7,
+        _ => return None,
+    };
+    Some(rank)
+}
+
+/// Severity rank of a parsed record. Prefers the `severity_num` column and
+/// falls back to normalizing `level` for output from older parser versions.
+pub fn record_severity(record: &Value) -> Option<u8> {
+    if let Some(num) = record.get("severity_num").and_then(|v| v.as_u64()) {
+        return u8::try_from(num).ok();
+    }
+    record
+        .get("level")
+        .and_then(|v| v.as_str())
+        .and_then(severity_rank)
+}
@@ -0,0 +1,202 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::Path;
+
+use crate::output::is_stdio;
+use crate::{FilterStats, Route, RouteStats};
+
+/// Every record was read, filtered and written.
+pub const EXIT_OK: i32 = 0;
+/// Nothing usable was produced: bad arguments, unreadable input, I/O error.
+pub const EXIT_FAILURE: i32 = 1;
+/// Stopped by Ctrl-C; output is partial (kept with --keep-partial, else discarded).
+/// Matches sf-parser's code for the same case.
+pub const EXIT_INTERRUPTED: i32 = 4;
+
+/// Bytes from the start of the input that identify it across runs.
+const FINGERPRINT_BYTES: u64 = 64 * 1024;
+
+/// How a run ended; each status maps to one exit code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunStatus {
+    Ok,
+    Interrupted,
+    Failed,
+}
+
+impl RunStatus {
+    pub fn exit_code(self) -> i32 {
+        match self {
+            RunStatus::Ok => EXIT_OK,
+            RunStatus::Interrupted => EXIT_INTERRUPTED,
+            RunStatus::Failed => EXIT_FAILURE,
+        }
+    }
+}
+
+/// Earliest and latest `timestamp` among the records written.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct TimeSpan {
+    pub first: Option<String>,
+    pub last: Option<String>,
+}
+
+impl TimeSpan {
+    /// Widen the span to include `timestamp`. sf-parser timestamps are
+    /// fixed-width ISO-8601 UTC, so string order is time order.
+    pub fn observe(&mut self, timestamp: &str) {
+        if timestamp.is_empty() {
+            return;
+        }
+        if self.first.as_deref().is_none_or(|first| timestamp < first) {
+            self.first = Some(timestamp.to_string());
+        }
+        if self.last.as_deref().is_none_or(|last| timestamp > last) {
+            self.last = Some(timestamp.to_string());
+        }
+    }
+}
+
+/// Identifies the input the summary describes. Stdin has no fingerprint.
+#[derive(Debug, Clone, Serialize)]
+pub struct InputSummary {
+    pub path: String,
+    pub bytes: Option<u64>,
+    /// SHA-256 of the first `fingerprint_len` bytes
+    pub fingerprint: Option<String>,
+    pub fingerprint_len: Option<u64>,
+}
+
+impl InputSummary {
+    pub fn for_path(path: &Path) -> Self {
+        let mut input = InputSummary {
+            path: path.display().to_string(),
+            bytes: None,
+            fingerprint: None,
+            fingerprint_len: None,
+        };
+        if is_stdio(path) {
+            return input;
+        }
+        input.bytes = fs::metadata(path).ok().map(|meta| meta.len());
+        if let Ok(file) = File::open(path) {
+            let mut prefix = Vec::new();
+            if file.take(FINGERPRINT_BYTES).read_to_end(&mut prefix).is_ok() {
+                let digest = Sha256::digest(&prefix);
+                input.fingerprint = Some(digest.iter().map(|b| format!("{:02x}", b)).collect());
+                input.fingerprint_len = Some(prefix.len() as u64);
+            }
+        }
+        input
+    }
+}
+
+/// The document written by `--summary-json`.
+#[derive(Debug, Clone, Serialize)]
+pub struct RunSummary {
+    pub tool: &'static str,
+    pub version: &'static str,
+    pub status: RunStatus,
+    pub exit_code: i32,
+    pub error: Option<String>,
+    pub input: InputSummary,
+    /// `None` when fanning out to `outputs`
+    pub output: Option<String>,
+    pub criteria: Value,
+    pub records_in: usize,
+    pub records_matched: usize,
+    pub records_context: usize,
+    pub records_written: usize,
+    pub time_span: TimeSpan,
+    pub duration_secs: f64,
+    pub records_per_sec: f64,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub outputs: Vec<OutputSummary>,
+}
+
+/// One output of a fan-out run.
+#[derive(Debug, Clone, Serialize)]
+pub struct OutputSummary {
+    pub name: String,
+    pub output: String,
+    pub criteria: Value,
+    pub records_matched: usize,
+    pub records_context: usize,
+    pub records_written: usize,
+    pub time_span: TimeSpan,
+}
+
+impl RunSummary {
+    pub fn new(input_path: &Path, output_path: &Path, criteria: Value, status: RunStatus) -> Self {
+        RunSummary {
+            tool: "sf-filter",
+            version: env!("CARGO_PKG_VERSION"),
+            status,
+            exit_code: status.exit_code(),
+            error: None,
+            input: InputSummary::for_path(input_path),
+            output: Some(output_path.display().to_string()),
+            criteria,
+            records_in: 0,
+            records_matched: 0,
+            records_context: 0,
+            records_written: 0,
+            time_span: TimeSpan::default(),
+            duration_secs: 0.0,
+            records_per_sec: 0.0,
+            outputs: Vec::new(),
+        }
+    }
+
+    pub fn with_stats(mut self, stats: &FilterStats) -> Self {
+        let duration_secs = stats.duration.as_secs_f64();
+        self.records_in = stats.records_in;
+        self.records_matched = stats.records_matched;
+        self.records_context = stats.records_context;
+        self.records_written = stats.records_written;
+        self.time_span = stats.time_span.clone();
+        self.duration_secs = duration_secs;
+        if duration_secs > 0.0 {
+            self.records_per_sec = stats.records_in as f64 / duration_secs;
+        }
+        self
+    }
+
+    /// Describe a fan-out run: one entry per route, with the totals from
+    /// `with_stats` at the top level.
+    pub fn with_routes(mut self, routes: &[Route], route_stats: &[RouteStats]) -> Self {
+        self.output = None;
+        self.criteria = Value::Null;
+        self.outputs = routes
+            .iter()
+            .map(|route| {
+                let stats = route_stats.iter().find(|s| s.name == route.name).map(|s| &s.stats);
+                OutputSummary {
+                    name: route.name.clone(),
+                    output: route.output.display().to_string(),
+                    criteria: route.filter.criteria(),
+                    records_matched: stats.map_or(0, |s| s.records_matched),
+                    records_context: stats.map_or(0, |s| s.records_context),
+                    records_written: stats.map_or(0, |s| s.records_written),
+                    time_span: stats.map(|s| s.time_span.clone()).unwrap_or_default(),
+                }
+            })
+            .collect();
+        self
+    }
+
+    pub fn with_error(mut self, error: impl ToString) -> Self {
+        self.error = Some(error.to_string());
+        self
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_vec_pretty(self)?)
+            .with_context(|| format!("Failed to write summary: {}", path.display()))
+    }
+}
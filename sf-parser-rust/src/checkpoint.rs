@@ -0,0 +1,105 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Bytes from the start of the input that identify it across runs.
+const FINGERPRINT_BYTES: u64 = 64 * 1024;
+
+/// Progress sidecar written next to NDJSON output so an interrupted or
+/// grown log can be resumed instead of re-parsed from line 1.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub parser_version: String,
+    pub input_path: String,
+    /// SHA-256 of the first `fingerprint_len` bytes of the input
+    pub input_fingerprint: String,
+    pub fingerprint_len: u64,
+    /// Input offset just past the last fully parsed line
+    pub byte_offset: u64,
+    /// Number of input lines parsed up to `byte_offset`
+    pub line_num: u32,
+    /// Length of the output file that corresponds to `byte_offset`
+    pub output_offset: u64,
+    /// Length of the `--rejects` file that corresponds to `byte_offset`,
+    /// when the run wrote one
+    #[serde(default)]
+    pub rejects_offset: Option<u64>,
+    pub schema_version: u32,
+    pub schema_fields: Vec<String>,
+}
+
+impl Checkpoint {
+    /// Sidecar path for an output file: `<output>.checkpoint`.
+    pub fn path_for(output_path: &Path) -> PathBuf {
+        let mut path = output_path.as_os_str().to_owned();
+        path.push(".checkpoint");
+        PathBuf::from(path)
+    }
+
+    pub fn load(path: &Path) -> Result<Checkpoint> {
+        let file = File::open(path)
+            .with_context(|| format!("Failed to open checkpoint: {}", path.display()))?;
+        serde_json::from_reader(file)
+            .with_context(|| format!("Invalid checkpoint file: {}", path.display()))
+    }
+
+    /// Write the checkpoint via a temp file and rename so a crash never
+    /// leaves a half-written sidecar behind.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        let tmp = PathBuf::from(tmp);
+        fs::write(&tmp, serde_json::to_vec_pretty(self)?)
+            .with_context(|| format!("Failed to write checkpoint: {}", tmp.display()))?;
+        fs::rename(&tmp, path)
+            .with_context(|| format!("Failed to write checkpoint: {}", path.display()))?;
+        Ok(())
+    }
+
+    /// Check that `input_path` is the same file this checkpoint was taken
+    /// from and that it still contains everything already parsed.
+    pub fn validate(&self, input_path: &Path) -> Result<()> {
+        if self.parser_version != env!("CARGO_PKG_VERSION") {
+            anyhow::bail!(
+                "Checkpoint was written by sf-parser v{}, this is v{}; re-parse without --resume",
+                self.parser_version,
+                env!("CARGO_PKG_VERSION")
+            );
+        }
+
+        let len = fs::metadata(input_path)
+            .with_context(|| format!("Failed to open input file: {}", input_path.display()))?
+            .len();
+        if len < self.byte_offset {
+            anyhow::bail!(
+                "Input {} is shorter than the checkpoint offset ({} < {} bytes); it was truncated or replaced",
+                input_path.display(), len, self.byte_offset
+            );
+        }
+
+        let (fingerprint, _) = input_fingerprint(input_path, self.fingerprint_len)?;
+        if fingerprint != self.input_fingerprint {
+            anyhow::bail!(
+                "Input {} does not match the checkpoint fingerprint; it was rotated or replaced",
+                input_path.display()
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Hash the first `min(limit, 64 KiB)` bytes of the input. Returns the hex
+/// digest and the number of bytes hashed.
+pub fn input_fingerprint(input_path: &Path, limit: u64) -> Result<(String, u64)> {
+    let file = File::open(input_path)
+        .with_context(|| format!("Failed to open input file: {}", input_path.display()))?;
+    let mut prefix = Vec::new();
+    file.take(limit.min(FINGERPRINT_BYTES)).read_to_end(&mut prefix)?;
+
+    let digest = Sha256::digest(&prefix);
+    let hex = digest.iter().map(|b| format!("{:02x}", b)).collect();
+    Ok((hex, prefix.len() as u64))
+}
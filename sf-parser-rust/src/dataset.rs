@@ -0,0 +1,390 @@
+use anyhow::{Context, Result};
+use arrow::datatypes::{DataType, Field, Schema, TimeUnit};
+use arrow::json::ReaderBuilder;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::{Compression, ZstdLevel};
+use parquet::file::properties::{EnabledStatistics, WriterProperties};
+use parquet::schema::types::ColumnPath;
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::open_files::{OpenFiles, MAX_OPEN_FILES};
+use crate::output::{AtomicFile, StagedDir};
+use crate::LogRecord;
+
+/// Rows per row group; each carries its own min/max statistics.
+const ROW_GROUP_ROWS: usize = 64 * 1024;
+
+/// Rows per file before a partition rolls over to the next `part-N`.
+const ROWS_PER_FILE: usize = 1024 * 1024;
+
+/// Hive's name for a partition whose value is missing.
+const DEFAULT_PARTITION: &str = "__HIVE_DEFAULT_PARTITION__";
+
+/// Spool directory inside the staged dataset.
+const SPOOL_DIR: &str = ".spool";
+
+/// Free-text columns where min/max statistics cost space and prune nothing.
+const NO_STATISTICS: &[&str] = &["raw_line", "content", "template"];
+
+/// Column type, widened as values are seen: Null < Boolean, and
+/// Null < Int64 < Float64; any other mix becomes Utf8.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ColumnType {
+    Null,
+    Boolean,
+    Int64,
+    Float64,
+    Utf8,
+    Timestamp,
+}
+
+impl ColumnType {
+    fn of(value: &Value) -> ColumnType {
+        match value {
+            Value::Null => ColumnType::Null,
+            Value::Bool(_) => ColumnType::Boolean,
+            Value::Number(n) if n.is_i64() => ColumnType::Int64,
+            Value::Number(_) => ColumnType::Float64,
+            _ => ColumnType::Utf8,
+        }
+    }
+
+    fn widen(self, other: ColumnType) -> ColumnType {
+        use ColumnType::*;
+        match (self, other) {
+            (a, b) if a == b => a,
+            (Null, t) | (t, Null) => t,
+            (Int64, Float64) | (Float64, Int64) => Float64,
+            _ => Utf8,
+        }
+    }
+
+    fn data_type(self) -> DataType {
+        match self {
+            ColumnType::Boolean => DataType::Boolean,
+            ColumnType::Int64 => DataType::Int64,
+            ColumnType::Float64 => DataType::Float64,
+            ColumnType::Null | ColumnType::Utf8 => DataType::Utf8,
+            ColumnType::Timestamp => DataType::Timestamp(TimeUnit::Microsecond, Some("+00:00".into())),
+        }
+    }
+}
+
+/// Writes records as a Hive-partitioned Parquet dataset:
+///
+/// ```text
+/// <dir>/date=2025-06-12/component=MS/part-0.parquet
+/// ```
+///
+/// `date` and `component` live only in the directory names, which is where
+/// DuckDB (`hive_partitioning`), polars and Spark read them from, so a query
+/// on one day or component never opens the other files. `timestamp` is a
+/// real UTC timestamp column, and row groups carry min/max statistics on it
+/// and on the ID columns so readers can skip most of a file too.
+///
+/// Parquet needs every column's type before the first row is written, but a
+/// field can be absent for most of a log and then turn out to be numeric, or
+/// numeric for hours and then carry a string. So records are spooled per
+/// partition as compact NDJSON during the single pass over the input, and
+/// `finish` converts them with one schema for the whole dataset. At most
+/// `MAX_OPEN_FILES` spools are open at a time; an idle one is closed and
+/// reopened for appending when its partition comes round again.
+///
+/// The dataset is built in a staging directory next to `dir` and moved into
+/// place by `finish`, so it never mixes with the files of an earlier run.
+/// An existing dataset is only replaced with `overwrite`, and dropping the
+/// writer without finishing removes the staging directory and leaves `dir`
+/// as it was.
+pub struct ParquetDataset {
+    staging: StagedDir,
+    /// Spool file per (date, component) partition
+    partitions: BTreeMap<(String, String), Spool>,
+    open_files: OpenFiles<(String, String)>,
+    columns: HashMap<String, ColumnType>,
+    dynamic_columns: HashSet<String>,
+    records: usize,
+}
+
+/// One partition's spooled rows; `file` is `None` while closed.
+struct Spool {
+    path: PathBuf,
+    file: Option<BufWriter<File>>,
+}
+
+impl ParquetDataset {
+    /// Start a dataset that `finish` writes to `dir`. Fails if `dir` is not
+    /// empty, unless `overwrite` is set and it holds only a dataset's
+    /// `date=` directories.
+    pub fn create(dir: &Path, overwrite: bool) -> Result<Self> {
+        let staging = StagedDir::create(dir, overwrite, "Parquet dataset", |entry| entry.starts_with("date="))?;
+        fs::create_dir(staging.path().join(SPOOL_DIR))?;
+        let mut columns = HashMap::new();
+        columns.insert("timestamp".to_string(), ColumnType::Timestamp);
+        Ok(ParquetDataset {
+            staging,
+            partitions: BTreeMap::new(),
+            open_files: OpenFiles::new(MAX_OPEN_FILES),
+            columns,
+            dynamic_columns: HashSet::new(),
+            records: 0,
+        })
+    }
+
+    pub fn write_record(&mut self, record: &LogRecord) -> Result<()> {
+        let Value::Object(fields) = serde_json::to_value(record)? else {
+            unreachable!("LogRecord serializes to an object");
+        };
+
+        let mut row = Map::new();
+        for (name, value) in fields {
+            // Partition columns are in the path, not the file
+            if name == "date" || name == "component" {
+                continue;
+            }
+            let value = match value {
+                Value::Null => continue,
+                // Only a timestamp that parsed fits the timestamp column
+                Value::String(_) if name == "timestamp" && record.date.is_empty() => continue,
+                Value::Array(_) | Value::Object(_) => Value::String(value.to_string()),
+                value => value,
+            };
+            if record.dynamic_fields.contains_key(&name) && !self.dynamic_columns.contains(&name) {
+                self.dynamic_columns.insert(name.clone());
+            }
+            let seen = ColumnType::of(&value);
+            self.columns
+                .entry(name.clone())
+                .and_modify(|column| {
+                    if *column != ColumnType::Timestamp {
+                        *column = column.widen(seen);
+                    }
+                })
+                .or_insert(seen);
+            row.insert(name, value);
+        }
+
+        let key = (
+            hive_value(&record.date),
+            hive_value(record.component.as_deref().unwrap_or("")),
+        );
+        if let Some(idle) = self.open_files.touch(&key) {
+            let spool = self.partitions.get_mut(&idle).expect("open file has a spool");
+            if let Some(mut file) = spool.file.take() {
+                file.flush()?;
+            }
+        }
+        if !self.partitions.contains_key(&key) {
+            let path = self.staging.path().join(SPOOL_DIR).join(format!("{}.ndjson", self.partitions.len()));
+            let file = File::create(&path)
+                .with_context(|| format!("Failed to create spool file: {}", path.display()))?;
+            self.partitions.insert(key.clone(), Spool { path, file: Some(BufWriter::new(file)) });
+        }
+        let spool = self.partitions.get_mut(&key).expect("spool was just created");
+        if spool.file.is_none() {
+            let file = fs::OpenOptions::new()
+                .append(true)
+                .open(&spool.path)
+                .with_context(|| format!("Failed to reopen spool file: {}", spool.path.display()))?;
+            spool.file = Some(BufWriter::new(file));
+        }
+        let file = spool.file.as_mut().expect("spool was just opened");
+        serde_json::to_writer(&mut *file, &row)?;
+        file.write_all(b"\n")?;
+        self.records += 1;
+        Ok(())
+    }
+
+    pub fn records(&self) -> usize {
+        self.records
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        for file in self.partitions.values_mut().filter_map(|spool| spool.file.as_mut()) {
+            file.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Convert every spooled partition to Parquet and move the dataset into
+    /// place. Returns the files written, relative to the dataset directory.
+    pub fn finish(mut self) -> Result<Vec<PathBuf>> {
+        for spool in self.partitions.values_mut() {
+            if let Some(mut file) = spool.file.take() {
+                file.flush()?;
+            }
+        }
+        let schema = Arc::new(self.schema());
+        let properties = writer_properties(&schema);
+        let staged = self.staging.path();
+
+        let mut files = Vec::new();
+        for ((date, component), spool) in &self.partitions {
+            let partition_dir = PathBuf::from(format!("date={}", date)).join(format!("component={}", component));
+            fs::create_dir_all(staged.join(&partition_dir))
+                .with_context(|| format!("Failed to create partition directory: {}", partition_dir.display()))?;
+
+            let batches = ReaderBuilder::new(schema.clone())
+                .with_batch_size(ROW_GROUP_ROWS)
+                .with_coerce_primitive(true)
+                .build(BufReader::new(File::open(&spool.path)?))?;
+
+            let mut part: Option<(PathBuf, ArrowWriter<AtomicFile>)> = None;
+            let mut part_rows = 0;
+            for batch in batches {
+                let batch = batch.with_context(|| format!("Failed to convert partition {}", partition_dir.display()))?;
+                if part.is_none() || part_rows >= ROWS_PER_FILE {
+                    if let Some((path, writer)) = part.take() {
+                        writer.into_inner()?.commit()?;
+                        files.push(path);
+                    }
+                    let path = partition_dir.join(format!("part-{}.parquet", files_in(&files, &partition_dir)));
+                    let file = AtomicFile::create(&staged.join(&path))?;
+                    part = Some((path, ArrowWriter::try_new(file, schema.clone(), Some(properties.clone()))?));
+                    part_rows = 0;
+                }
+                let (_, writer) = part.as_mut().expect("part was just opened");
+                writer.write(&batch)?;
+                part_rows += batch.num_rows();
+            }
+            if let Some((path, writer)) = part {
+                writer.into_inner()?.commit()?;
+                files.push(path);
+            }
+        }
+        fs::remove_dir_all(staged.join(SPOOL_DIR))?;
+
+        self.staging.commit()?;
+        Ok(files)
+    }
+
+    /// Every column seen, typed for the whole dataset: fixed columns first,
+    /// then dynamic fields, each group sorted by name.
+    fn schema(&self) -> Schema {
+        let mut names: Vec<&String> = self.columns.keys().collect();
+        names.sort_by_key(|name| (self.dynamic_columns.contains(*name), *name));
+        Schema::new(
+            names
+                .into_iter()
+                .map(|name| Field::new(name, self.columns[name].data_type(), true))
+                .collect::<Vec<_>>(),
+        )
+    }
+}
+
+/// ZSTD-compressed, with row-group statistics on every column except long
+/// free text.
+fn writer_properties(schema: &Schema) -> WriterProperties {
+    let mut builder = WriterProperties::builder()
+        .set_compression(Compression::ZSTD(ZstdLevel::default()))
+        .set_max_row_group_size(ROW_GROUP_ROWS)
+        .set_statistics_enabled(EnabledStatistics::Chunk);
+    for name in NO_STATISTICS {
+        if schema.field_with_name(name).is_ok() {
+            builder = builder.set_column_statistics_enabled(ColumnPath::from(*name), EnabledStatistics::None);
+        }
+    }
+    builder.build()
+}
+
+fn files_in(files: &[PathBuf], dir: &Path) -> usize {
+    files.iter().filter(|file| file.parent() == Some(dir)).count()
+}
+
+/// A partition value as a directory name: Hive escapes anything outside a
+/// safe set as `%XX`, and a missing value is `__HIVE_DEFAULT_PARTITION__`.
+fn hive_value(value: &str) -> String {
+    if value.is_empty() {
+        return DEFAULT_PARTITION.to_string();
+    }
+    let mut escaped = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_') {
+            escaped.push(byte as char);
+        } else {
+            escaped.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SolidFireParser;
+    use tempfile::TempDir;
+
+    fn record(parser: &SolidFireParser, day: u32, component: &str) -> LogRecord {
+        parser.parse_line(&format!(
+            "2025-06-{:02}T08:35:00.177183Z icpbasi03037 master-1[112875]: [APP-5] [{}] 2069183 Scheduler a.cpp:1:F| n=1", day, component), 1)
+    }
+
+    #[test]
+    fn test_replace_existing() {
+        let parser = SolidFireParser::new().unwrap();
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path().join("dataset");
+        let write = |day: u32, overwrite: bool| -> Result<Vec<PathBuf>> {
+            let mut dataset = ParquetDataset::create(&dir, overwrite)?;
+            dataset.write_record(&record(&parser, day, "MS"))?;
+            dataset.finish()
+        };
+
+        write(5, false).unwrap();
+        assert!(write(12, false).is_err());
+        // An unfinished run leaves the existing dataset as it was
+        let mut dataset = ParquetDataset::create(&dir, true).unwrap();
+        dataset.write_record(&record(&parser, 12, "MS")).unwrap();
+        drop(dataset);
+        assert!(dir.join("date=2025-06-05").exists());
+
+        // Replacing drops partitions the new run doesn't have
+        assert_eq!(write(12, true).unwrap(), [PathBuf::from("date=2025-06-12/component=MS/part-0.parquet")]);
+        assert!(!dir.join("date=2025-06-05").exists());
+        assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 1);
+
+        // Never replace a directory that isn't a dataset
+        fs::write(dir.join("notes.txt"), "keep me").unwrap();
+        let error = ParquetDataset::create(&dir, true).err().unwrap().to_string();
+        assert!(error.contains("notes.txt is not part of a Parquet dataset"), "{}", error);
+        assert!(ParquetDataset::create(Path::new("."), true).is_err());
+    }
+
+    #[test]
+    fn test_unparsed_timestamp() {
+        let parser = SolidFireParser::new().unwrap();
+        let temp_dir = TempDir::new().unwrap();
+        let mut dataset = ParquetDataset::create(&temp_dir.path().join("dataset"), false).unwrap();
+        let record = parser.parse_line("sf-master.info:2025-06-12T09:08:32.758370Z icpbasi03037 master-1[112875]: [APP-5] [MS] x", 1);
+        assert!(record.date.is_empty() && !record.timestamp.is_empty());
+        dataset.write_record(&record).unwrap();
+        assert_eq!(dataset.finish().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_open_spool_limit() {
+        let parser = SolidFireParser::new().unwrap();
+        let temp_dir = TempDir::new().unwrap();
+        let mut dataset = ParquetDataset::create(&temp_dir.path().join("dataset"), false).unwrap();
+        dataset.open_files = OpenFiles::new(2);
+        for n in 0..30 {
+            dataset.write_record(&record(&parser, 1 + n % 5, ["MS", "Snaps"][n as usize % 2])).unwrap();
+        }
+        assert!(dataset.partitions.values().filter(|spool| spool.file.is_some()).count() <= 2);
+
+        let files = dataset.finish().unwrap();
+        assert_eq!(files.len(), 10);
+        let rows: usize = files.iter().map(|file| {
+            let file = File::open(temp_dir.path().join("dataset").join(file)).unwrap();
+            parquet::file::reader::SerializedFileReader::new(file).map(|reader| {
+                use parquet::file::reader::FileReader;
+                reader.metadata().file_metadata().num_rows() as usize
+            }).unwrap()
+        }).sum();
+        assert_eq!(rows, 30);
+    }
+}
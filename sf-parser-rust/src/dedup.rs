@@ -0,0 +1,111 @@
+use chrono::DateTime;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::time::Duration;
+
+use crate::LogRecord;
+
+/// Drops records already seen within a sliding time window.
+///
+/// Two records are duplicates when they share `timestamp`, `hostname`, `pid`,
+/// `thread` and content. Keys are kept in per-second buckets, and buckets
+/// that fall more than `window` behind the newest timestamp are evicted, so
+/// memory is bounded by the log rate times the window rather than by the
+/// input size. Duplicates further apart than the window are not detected;
+/// merging inputs by timestamp keeps real overlaps well inside it.
+///
+/// Records without a timestamp (unparsed lines) are never dropped: the same
+/// continuation or garbage line can legitimately repeat.
+#[derive(Debug)]
+pub struct Deduplicator {
+    window_secs: i64,
+    buckets: BTreeMap<i64, HashSet<u64>>,
+    newest: Option<i64>,
+    dropped: usize,
+}
+
+impl Deduplicator {
+    pub fn new(window: Duration) -> Self {
+        Deduplicator {
+            window_secs: window.as_secs().max(1) as i64,
+            buckets: BTreeMap::new(),
+            newest: None,
+            dropped: 0,
+        }
+    }
+
+    /// Record `record` and return whether it duplicates one seen before.
+    pub fn is_duplicate(&mut self, record: &LogRecord) -> bool {
+        let Ok(timestamp) = DateTime::parse_from_rfc3339(&record.timestamp) else {
+            return false;
+        };
+        let second = timestamp.timestamp();
+
+        if let Some(newest) = self.newest {
+            // Older than anything still held; can't tell, so keep it
+            if second < newest - self.window_secs {
+                return false;
+            }
+        }
+
+        let inserted = self
+            .buckets
+            .entry(second)
+            .or_default()
+            .insert(record_key(record));
+
+        if self.newest.is_none_or(|newest| second > newest) {
+            self.newest = Some(second);
+            let horizon = second - self.window_secs;
+            self.buckets = self.buckets.split_off(&horizon);
+        }
+
+        if !inserted {
+            self.dropped += 1;
+        }
+        !inserted
+    }
+
+    /// Number of records reported as duplicates so far.
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+}
+
+/// Hash of the fields that identify a log event. Records without `content`
+/// fall back to the raw line.
+fn record_key(record: &LogRecord) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    record.timestamp.hash(&mut hasher);
+    record.hostname.hash(&mut hasher);
+    record.pid.hash(&mut hasher);
+    record.thread.hash(&mut hasher);
+    record.content.as_deref().unwrap_or(&record.raw_line).hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SolidFireParser;
+
+    #[test]
+    fn test_window_eviction() {
+        let parser = SolidFireParser::new().unwrap();
+        let line = "2025-06-05T00:20:01.858372Z icpbasi03037 master-1[112875]: [APP-5] [MS] 2069182 BSDirector ms/ClusterStatistics.cpp:1452:GetBlockDriveUsageFromStats| serviceID=1";
+        let early = parser.parse_line(line, 1);
+        let mut late = parser.parse_line(line, 2);
+        late.timestamp = "2025-06-05T00:25:00.000000Z".to_string();
+        let garbage = parser.parse_line("garbage", 3);
+
+        // Memory is bounded: keys older than the window are forgotten
+        let mut dedup = Deduplicator::new(Duration::from_secs(60));
+        assert!(!dedup.is_duplicate(&early));
+        assert!(dedup.is_duplicate(&early));
+        assert!(!dedup.is_duplicate(&late));
+        assert!(!dedup.is_duplicate(&early));
+        assert!(!dedup.is_duplicate(&garbage) && !dedup.is_duplicate(&garbage));
+        assert_eq!(dedup.dropped(), 1);
+    }
+}
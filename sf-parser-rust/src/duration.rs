@@ -0,0 +1,38 @@
+use anyhow::{Context, Result};
+use std::time::Duration;
+
+/// Parse a duration such as `90`, `90s`, `5m` or `1h`; a bare number is
+/// seconds.
+pub fn parse_duration(value: &str) -> Result<Duration> {
+    let value = value.trim();
+    let (number, unit) = match value.find(|c: char| !c.is_ascii_digit()) {
+        Some(pos) => value.split_at(pos),
+        None => (value, "s"),
+    };
+    let number: u64 = number.parse()
+        .with_context(|| format!("Invalid duration: {}", value))?;
+    let unit_secs: u64 = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        _ => anyhow::bail!("Invalid duration unit in {} (expected s, m or h)", value),
+    };
+    let secs = number.checked_mul(unit_secs)
+        .with_context(|| format!("Duration too long: {}", value))?;
+    Ok(Duration::from_secs(secs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("5m").unwrap(), Duration::from_secs(300));
+        assert_eq!(parse_duration(" 2h ").unwrap(), Duration::from_secs(7200));
+        assert_eq!(parse_duration("90").unwrap(), Duration::from_secs(90));
+        assert!(parse_duration("5d").is_err());
+        assert!(parse_duration("m").is_err());
+        assert!(parse_duration("18446744073709551615h").is_err());
+    }
+}
@@ -0,0 +1,302 @@
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::reader::{decode_line, DecodedLine};
+
+/// How long to sleep when the followed file has no new data.
+pub const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+const READ_CHUNK_BYTES: usize = 64 * 1024;
+
+/// Identifies the file behind a path so rotation can be detected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FileIdentity {
+    dev: u64,
+    ino: u64,
+}
+
+#[cfg(unix)]
+fn file_identity(meta: &fs::Metadata) -> Option<FileIdentity> {
+    use std::os::unix::fs::MetadataExt;
+    Some(FileIdentity {
+        dev: meta.dev(),
+        ino: meta.ino(),
+    })
+}
+
+#[cfg(not(unix))]
+fn file_identity(_meta: &fs::Metadata) -> Option<FileIdentity> {
+    None
+}
+
+/// Tails a log file like `tail -F`: it keeps reading as the file grows,
+/// reopens the path when the file is rotated or recreated, and starts over
+/// when the file is truncated in place.
+pub struct LogFollower {
+    path: PathBuf,
+    max_line_bytes: usize,
+    file: Option<File>,
+    identity: Option<FileIdentity>,
+    offset: u64,
+    start_at_end: bool,
+    pending: Vec<u8>,
+    pending_truncated: bool,
+    /// Following started at the end of a file that ended mid-line; the rest
+    /// of that line is dropped rather than emitted as a fragment
+    skip_partial_line: bool,
+    line_num: u32,
+}
+
+impl LogFollower {
+    /// Follow `path`. With `from_start` the existing contents are emitted
+    /// first; otherwise only lines appended after startup are.
+    pub fn new(path: &Path, max_line_bytes: usize, from_start: bool) -> Self {
+        LogFollower {
+            path: path.to_path_buf(),
+            max_line_bytes,
+            file: None,
+            identity: None,
+            offset: 0,
+            start_at_end: !from_start,
+            pending: Vec::new(),
+            pending_truncated: false,
+            skip_partial_line: false,
+            line_num: 0,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Return every complete line that has been appended since the last
+    /// poll, paired with its line number in the current file.
+    pub fn poll(&mut self) -> io::Result<Vec<(u32, DecodedLine)>> {
+        let mut lines = Vec::new();
+
+        if self.file.is_none() && !self.open()? {
+            return Ok(lines);
+        }
+
+        self.read_available(&mut lines)?;
+        if lines.is_empty() {
+            self.check_rotation(&mut lines)?;
+        }
+
+        Ok(lines)
+    }
+
+    /// Open the path if it exists. The first open honours `start_at_end`;
+    /// files that appear later (after rotation) are always read from the
+    /// beginning.
+    fn open(&mut self) -> io::Result<bool> {
+        let mut file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+            Err(e) => return Err(e),
+        };
+        let meta = file.metadata()?;
+
+        self.offset = if self.start_at_end {
+            // Count the lines we skip so line numbers still match the file;
+            // counting leaves the handle at the end of what was counted
+            let (line_num, ends_mid_line) = count_lines(&mut file)?;
+            self.line_num = line_num;
+            self.skip_partial_line = ends_mid_line;
+            file.stream_position()?
+        } else {
+            self.line_num = 0;
+            self.skip_partial_line = false;
+            0
+        };
+        self.start_at_end = false;
+        self.identity = file_identity(&meta);
+        self.file = Some(file);
+        Ok(true)
+    }
+
+    fn read_available(&mut self, lines: &mut Vec<(u32, DecodedLine)>) -> io::Result<()> {
+        let Some(mut file) = self.file.take() else {
+            return Ok(());
+        };
+        let result = self.read_from(&mut file, lines);
+        self.file = Some(file);
+        result
+    }
+
+    fn read_from(&mut self, file: &mut File, lines: &mut Vec<(u32, DecodedLine)>) -> io::Result<()> {
+        let mut buf = vec![0u8; READ_CHUNK_BYTES];
+        loop {
+            let n = match file.read(&mut buf) {
+                Ok(0) => return Ok(()),
+                Ok(n) => n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            self.offset += n as u64;
+
+            for (i, segment) in buf[..n].split(|&b| b == b'\n').enumerate() {
+                if i > 0 {
+                    self.finish_line(lines);
+                }
+                let room = self.max_line_bytes.saturating_sub(self.pending.len());
+                if segment.len() > room {
+                    self.pending_truncated = true;
+                }
+                self.pending.extend_from_slice(&segment[..segment.len().min(room)]);
+            }
+        }
+    }
+
+    fn finish_line(&mut self, lines: &mut Vec<(u32, DecodedLine)>) {
+        let bytes = std::mem::take(&mut self.pending);
+        let truncated = std::mem::replace(&mut self.pending_truncated, false);
+        self.line_num += 1;
+        if std::mem::take(&mut self.skip_partial_line) {
+            return;
+        }
+        lines.push((self.line_num, decode_line(bytes, truncated)));
+    }
+
+    /// At end of file, decide whether the path now points at a new file
+    /// (rotation) or the current file shrank (truncation).
+    fn check_rotation(&mut self, lines: &mut Vec<(u32, DecodedLine)>) -> io::Result<()> {
+        let meta = match fs::metadata(&self.path) {
+            Ok(meta) => meta,
+            // Rotated away and not recreated yet; keep the old handle
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e),
+        };
+
+        let rotated = match (self.identity, file_identity(&meta)) {
+            (Some(old), Some(new)) => old != new,
+            _ => false,
+        };
+
+        if rotated {
+            // Lines may have been appended to the old file between the last
+            // read and the rename; drain it before letting go of the handle.
+            // Its trailing partial line is then complete
+            self.read_available(lines)?;
+            if !self.pending.is_empty() {
+                self.finish_line(lines);
+            }
+            self.file = None;
+            if self.open()? {
+                self.read_available(lines)?;
+            }
+        } else if meta.len() < self.offset {
+            if let Some(file) = self.file.as_mut() {
+                file.seek(SeekFrom::Start(0))?;
+            }
+            self.offset = 0;
+            self.line_num = 0;
+            self.pending.clear();
+            self.pending_truncated = false;
+            self.skip_partial_line = false;
+            self.read_available(lines)?;
+        }
+        Ok(())
+    }
+}
+
+/// Count the lines from the current position to the end of the file, and
+/// say whether the file ends partway through one.
+fn count_lines(file: &mut File) -> io::Result<(u32, bool)> {
+    let mut buf = vec![0u8; READ_CHUNK_BYTES];
+    let mut count = 0u32;
+    let mut ends_mid_line = false;
+    loop {
+        match file.read(&mut buf) {
+            Ok(0) => return Ok((count, ends_mid_line)),
+            Ok(n) => {
+                count += buf[..n].iter().filter(|&&b| b == b'\n').count() as u32;
+                ends_mid_line = buf[n - 1] != b'\n';
+            }
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_growth_and_rotation() {
+        let temp_dir = TempDir::new().unwrap();
+        let log_path = temp_dir.path().join("sf-master.info");
+        fs::write(&log_path, "one\n").unwrap();
+
+        // Existing contents are skipped but still counted for line numbers
+        let mut follower = LogFollower::new(&log_path, 1024, false);
+        assert!(follower.poll().unwrap().is_empty());
+
+        // A partial line is held back until its newline arrives
+        let mut file = fs::OpenOptions::new().append(true).open(&log_path).unwrap();
+        write!(file, "tw").unwrap();
+        assert!(follower.poll().unwrap().is_empty());
+        writeln!(file, "o").unwrap();
+        let lines = follower.poll().unwrap();
+        assert_eq!(lines.len(), 1);
+        assert_eq!((lines[0].0, lines[0].1.text.as_str()), (2, "two"));
+
+        // Rotation: the path now names a fresh file, read from its start
+        fs::rename(&log_path, temp_dir.path().join("sf-master.info.1")).unwrap();
+        fs::write(&log_path, "three\n").unwrap();
+        let lines = follower.poll().unwrap();
+        assert_eq!(lines.len(), 1);
+        assert_eq!((lines[0].0, lines[0].1.text.as_str()), (1, "three"));
+
+        // Truncation in place starts over
+        fs::write(&log_path, "").unwrap();
+        assert!(follower.poll().unwrap().is_empty());
+        fs::write(&log_path, "four\n").unwrap();
+        let lines = follower.poll().unwrap();
+        assert_eq!((lines[0].0, lines[0].1.text.as_str()), (1, "four"));
+    }
+
+    #[test]
+    fn test_start_mid_line() {
+        let temp_dir = TempDir::new().unwrap();
+        let log_path = temp_dir.path().join("sf-master.info");
+        fs::write(&log_path, "one\npart").unwrap();
+
+        // The line being written at startup is existing content, not a fragment to emit
+        let mut follower = LogFollower::new(&log_path, 1024, false);
+        assert!(follower.poll().unwrap().is_empty());
+        let mut file = fs::OpenOptions::new().append(true).open(&log_path).unwrap();
+        write!(file, "ial\nthree\n").unwrap();
+        let lines = follower.poll().unwrap();
+        assert_eq!(lines.len(), 1);
+        assert_eq!((lines[0].0, lines[0].1.text.as_str()), (3, "three"));
+    }
+
+    #[test]
+    fn test_rotation_drains_old_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let log_path = temp_dir.path().join("sf-master.info");
+        fs::write(&log_path, "one\n").unwrap();
+
+        let mut follower = LogFollower::new(&log_path, 1024, true);
+        let lines = follower.poll().unwrap();
+        assert_eq!(lines.len(), 1);
+
+        // Written to the old file after the last read, just before rotation
+        let mut old = fs::OpenOptions::new().append(true).open(&log_path).unwrap();
+        write!(old, "two\nthree").unwrap();
+        fs::rename(&log_path, temp_dir.path().join("sf-master.info.1")).unwrap();
+        fs::write(&log_path, "four\n").unwrap();
+
+        let mut lines = Vec::new();
+        follower.check_rotation(&mut lines).unwrap();
+        let texts: Vec<&str> = lines.iter().map(|(_, line)| line.text.as_str()).collect();
+        assert_eq!(texts, ["two", "three", "four"]);
+        assert_eq!(lines[2].0, 1);
+    }
+}
@@ -0,0 +1,121 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Which parse path produced a record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Full header matched by the basic regex
+    Basic,
+    /// Header followed by `method CALL: ...`
+    Call,
+    /// Header regex failed; timestamp/host/process split on whitespace
+    Minimal,
+    /// Nothing usable; the whole line is kept as content
+    Unparsed,
+}
+
+impl LogFormat {
+    /// Every parse path, in the order they are tried.
+    pub const ALL: [LogFormat; 4] = [
+        LogFormat::Basic,
+        LogFormat::Call,
+        LogFormat::Minimal,
+        LogFormat::Unparsed,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LogFormat::Basic => "basic",
+            LogFormat::Call => "call",
+            LogFormat::Minimal => "minimal",
+            LogFormat::Unparsed => "unparsed",
+        }
+    }
+}
+
+impl fmt::Display for LogFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Machine-readable reason a line was not fully parsed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParseErrorCode {
+    /// The structured header regexes did not match
+    HeaderMismatch,
+    /// The first token is not an ISO-8601 timestamp
+    InvalidTimestamp,
+    /// Fewer than three whitespace-separated tokens
+    InsufficientParts,
+}
+
+impl ParseErrorCode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ParseErrorCode::HeaderMismatch => "header_mismatch",
+            ParseErrorCode::InvalidTimestamp => "invalid_timestamp",
+            ParseErrorCode::InsufficientParts => "insufficient_parts",
+        }
+    }
+}
+
+impl fmt::Display for ParseErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Per-format record counts for a run.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FormatCounts {
+    counts: [usize; LogFormat::ALL.len()],
+}
+
+impl FormatCounts {
+    pub fn record(&mut self, format: LogFormat) {
+        self.counts[format as usize] += 1;
+    }
+
+    pub fn get(&self, format: LogFormat) -> usize {
+        self.counts[format as usize]
+    }
+
+    pub fn total(&self) -> usize {
+        self.counts.iter().sum()
+    }
+
+    /// Records that fell back to the minimal or unparsed paths.
+    pub fn failed(&self) -> usize {
+        self.get(LogFormat::Minimal) + self.get(LogFormat::Unparsed)
+    }
+
+    /// Fraction of records that failed to parse (0.0 for an empty run).
+    pub fn error_rate(&self) -> f64 {
+        match self.total() {
+            0 => 0.0,
+            total => self.failed() as f64 / total as f64,
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (LogFormat, usize)> + '_ {
+        LogFormat::ALL.iter().map(move |&f| (f, self.get(f)))
+    }
+
+    /// Render the per-format summary table printed at the end of a run.
+    pub fn summary_table(&self) -> String {
+        let total = self.total().max(1) as f64;
+        let mut table = String::from("Parse paths:\n");
+        for (format, count) in self.iter() {
+            table.push_str(&format!(
+                "  {:<10} {:>10}  {:>5.1}%\n",
+                format.as_str(),
+                count,
+                count as f64 / total * 100.0
+            ));
+        }
+        table
+    }
+}
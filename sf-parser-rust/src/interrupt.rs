@@ -0,0 +1,23 @@
+use anyhow::{Context, Result};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Install a Ctrl-C handler and return the flag it sets. Long-running loops
+/// poll the flag between chunks so they can stop cleanly; a second Ctrl-C
+/// exits immediately.
+pub fn install() -> Result<Arc<AtomicBool>> {
+    let flag = Arc::new(AtomicBool::new(false));
+    let handler_flag = Arc::clone(&flag);
+    ctrlc::set_handler(move || {
+        if handler_flag.swap(true, Ordering::SeqCst) {
+            std::process::exit(130);
+        }
+        status!("\nInterrupt received, finishing the current chunk (Ctrl-C again to abort)");
+    })
+    .context("Failed to install Ctrl-C handler")?;
+    Ok(flag)
+}
+
+pub fn requested(flag: &Option<Arc<AtomicBool>>) -> bool {
+    flag.as_ref().is_some_and(|f| f.load(Ordering::SeqCst))
+}
@@ -4,16 +4,106 @@ use clap::{Arg, Command};
 use rayon::prelude::*;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 use std::collections::{HashMap, HashSet};
-use std::fs::File;
-use std::io::{BufRead, BufReader, Write};
-use std::path::Path;
-use std::sync::Arc;
-use std::time::Instant;
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+
+/// Set by `--quiet` to silence `status!` output.
+pub static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// Print human-oriented progress to stderr unless `--quiet` was given, so
+/// stdout stays free for piped output.
+macro_rules! status {
+    ($($arg:tt)*) => {
+        if !$crate::QUIET.load(std::sync::atomic::Ordering::Relaxed) {
+            eprintln!($($arg)*);
+        }
+    };
+}
+
+mod checkpoint;
+mod dataset;
+mod dedup;
+mod duration;
+mod follow;
+mod format;
+mod interrupt;
+mod merge;
+mod open_files;
+mod output;
+mod quality;
+mod reader;
+mod redact;
+mod schema;
+mod severity;
+mod source;
+mod split;
+mod summary;
+mod templates;
+
+pub use checkpoint::{input_fingerprint, Checkpoint};
+pub use dataset::ParquetDataset;
+pub use dedup::Deduplicator;
+use duration::parse_duration;
+pub use follow::{LogFollower, POLL_INTERVAL};
+pub use format::{FormatCounts, LogFormat, ParseErrorCode};
+pub use merge::{InputLine, MergedLines};
+pub use output::{is_broken_pipe, is_stdio, AtomicFile, OutputFormat, RecordWriter};
+pub use quality::{parse_rate, quality_report, FailureShapes};
+pub use reader::{spool_stdin, DecodeError, DecodedLine, LossyLines, DEFAULT_MAX_LINE_BYTES};
+pub use redact::{RedactRule, Redactor};
+pub use schema::IncrementalSchema;
+pub use severity::Severity;
+pub use source::SourceLocation;
+pub use split::{PartitionSummary, RecordSink, SplitBy, SplitWriter, MANIFEST_FILE};
+pub use summary::{InputSummary, RunStatus, RunSummary, TimeSpan, EXIT_FAILURE, EXIT_OK};
+pub use templates::{Template, TemplateMiner};
+
+/// Names of the fixed `LogRecord` columns, read off a serialized record so
+/// they can't fall behind the struct. A parsed `key=value` pair with one of
+/// these names would be flattened next to the column and write the key
+/// twice, so it is kept as `sf_<key>` instead (`severity=Error` becomes
+/// `sf_severity`).
+pub fn reserved_fields() -> &'static HashSet<String> {
+    static RESERVED: OnceLock<HashSet<String>> = OnceLock::new();
+    RESERVED.get_or_init(|| {
+        match serde_json::to_value(LogRecord::unparsed("", 0)) {
+            Ok(serde_json::Value::Object(columns)) => columns.into_iter().map(|(column, _)| column).collect(),
+            _ => unreachable!("a LogRecord serializes to an object"),
+        }
+    })
+}
+
+/// The dynamic field name for a parsed key, renamed if it is reserved.
+fn dynamic_field_name(key: &str) -> Cow<'_, str> {
+    if reserved_fields().contains(key) {
+        Cow::Owned(format!("sf_{}", key))
+    } else {
+        Cow::Borrowed(key)
+    }
+}
+
+/// The schema's fields that are renamed keys, sorted.
+fn renamed_fields(fields: &HashSet<String>) -> Vec<String> {
+    let mut renamed: Vec<String> = fields
+        .iter()
+        .filter(|field| field.strip_prefix("sf_").is_some_and(|key| reserved_fields().contains(key)))
+        .cloned()
+        .collect();
+    renamed.sort();
+    renamed
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LogRecord {
     pub line_num: u32,
+    /// Which input the line came from when several are merged
+    pub input_file: Option<String>,
     pub date: String,
     pub time: String,
     pub timestamp: String,
@@ -21,25 +111,133 @@ pub struct LogRecord {
     pub process: Option<String>,
     pub pid: Option<u32>,
     pub level: Option<String>,
+    pub severity: Option<Severity>,
+    pub severity_num: Option<u8>,
     pub component: Option<String>,
     pub thread: Option<u32>,
     pub class: Option<String>,
     pub source: Option<String>,
+    pub src_file: Option<String>,
+    pub src_line: Option<u32>,
+    pub src_func: Option<String>,
+    pub subsystem: Option<String>,
     pub content: Option<String>,
+    /// Mined message template, with `--templates`
+    pub template_id: Option<u32>,
+    pub template: Option<String>,
     pub raw_line: String,
-    pub parse_error: Option<String>,
+    pub format: LogFormat,
+    pub parse_error: Option<ParseErrorCode>,
+    pub decode_error: Option<DecodeError>,
     
     // Dynamic fields - we'll use a HashMap for flexibility
     #[serde(flatten)]
     pub dynamic_fields: HashMap<String, serde_json::Value>,
 }
 
+impl LogRecord {
+    /// A line nothing could be split out of; it is kept whole as `content`.
+    fn unparsed(line: &str, line_num: u32) -> LogRecord {
+        LogRecord {
+            line_num,
+            input_file: None,
+            date: "".to_string(),
+            time: "".to_string(),
+            timestamp: "".to_string(),
+            hostname: None,
+            process: None,
+            pid: None,
+            level: None,
+            severity: None,
+            severity_num: None,
+            component: None,
+            thread: None,
+            class: None,
+            source: None,
+            src_file: None,
+            src_line: None,
+            src_func: None,
+            subsystem: None,
+            content: Some(line.to_string()),
+            template_id: None,
+            template: None,
+            raw_line: line.to_string(),
+            format: LogFormat::Unparsed,
+            parse_error: Some(ParseErrorCode::InsufficientParts),
+            decode_error: None,
+            dynamic_fields: HashMap::new(),
+        }
+    }
+}
+
+/// Options controlling a `parse_file` run.
+#[derive(Debug, Clone)]
+pub struct ParseOptions {
+    pub chunk_size: usize,
+    /// Write lines that fail to parse here as `line_num<TAB>raw_line`
+    pub rejects_path: Option<PathBuf>,
+    /// Lines longer than this many bytes are truncated
+    pub max_line_bytes: usize,
+    pub format: OutputFormat,
+    /// Continue from the checkpoint next to the output instead of line 1
+    pub resume: bool,
+    /// On interrupt, close and keep the JSON written so far
+    pub keep_partial: bool,
+    /// Drop records seen before within this window (see `Deduplicator`)
+    pub dedup_window: Option<Duration>,
+    /// Mine message templates and write their catalog here
+    pub templates_path: Option<PathBuf>,
+    /// Write one file per partition into the output directory
+    pub split_by: Option<SplitBy>,
+    /// Replace an existing Parquet dataset or split output in the output directory
+    pub overwrite: bool,
+    /// Set by the Ctrl-C handler; checked between chunks
+    pub interrupt: Option<Arc<AtomicBool>>,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions {
+            chunk_size: 1000,
+            rejects_path: None,
+            max_line_bytes: DEFAULT_MAX_LINE_BYTES,
+            format: OutputFormat::Json,
+            resume: false,
+            keep_partial: false,
+            dedup_window: None,
+            templates_path: None,
+            split_by: None,
+            overwrite: false,
+            interrupt: None,
+        }
+    }
+}
+
+/// Statistics collected over a `parse_file` run.
+#[derive(Debug, Clone, Default)]
+pub struct ParseStats {
+    pub inputs: Vec<InputSummary>,
+    pub total_lines: usize,
+    pub dynamic_field_count: usize,
+    pub format_counts: FormatCounts,
+    pub failure_shapes: FailureShapes,
+    pub rejected_lines: usize,
+    pub decode_errors: usize,
+    pub duplicates_dropped: usize,
+    pub template_count: usize,
+    /// Dynamic fields renamed to `sf_<key>` because `<key>` is a fixed column
+    pub renamed_fields: Vec<String>,
+    pub time_span: TimeSpan,
+    pub duration: Duration,
+    /// The run stopped at Ctrl-C before reaching the end of the input
+    pub interrupted: bool,
+}
+
 #[derive(Debug)]
 pub struct SolidFireParser {
     basic_regex: Regex,
     call_regex: Regex,
-    kv_regex: Regex,
-    known_fields: HashSet<String>,
+    redactor: Option<Redactor>,
 }
 
 impl SolidFireParser {
@@ -53,25 +251,31 @@ impl SolidFireParser {
             r"^(\d{4}-\d{2}-\d{2}T\d{2}:\d{2}:\d{2}\.\d+Z)\s+(\S+)\s+([^\[]+)\[(\d+)\]:\s+\[([^\]]+)\]\s+\[([^\]]+)\]\s+(\d+)\s+(\S+)\s+([^|]+)\|([^|]+)\s+CALL:\s*(.*)"
         ).context("Failed to compile call regex")?;
         
-        let kv_regex = Regex::new(r"(\w+)=([^\s]+)")
-            .context("Failed to compile key-value regex")?;
-        
-        // Pre-define known fields that we expect to see frequently
-        let known_fields: HashSet<String> = [
-            "serviceID", "usedBytes", "nodeID", "sliceID", "snapshotID", 
-            "groupID", "volumeID", "requestID", "status", "code", "type",
-            "severity", "driveID", "masterID", "replicationID"
-        ].iter().map(|s| s.to_string()).collect();
-        
         Ok(SolidFireParser {
             basic_regex,
             call_regex,
-            kv_regex,
-            known_fields,
+            redactor: None,
         })
     }
     
+    /// Pseudonymize every line before it is parsed (and before schema
+    /// discovery sees it).
+    pub fn with_redactor(mut self, redactor: Redactor) -> Self {
+        self.redactor = Some(redactor);
+        self
+    }
+    
+    fn redact<'a>(&self, line: &'a str) -> Cow<'a, str> {
+        match self.redactor {
+            Some(ref redactor) => redactor.redact(line),
+            None => Cow::Borrowed(line),
+        }
+    }
+    
     pub fn parse_line(&self, line: &str, line_num: u32) -> LogRecord {
+        let line = self.redact(line);
+        let line = line.as_ref();
+        
         // Try basic format first
         if let Some(captures) = self.basic_regex.captures(line) {
             return self.parse_basic_format(line, line_num, &captures);
@@ -89,26 +293,41 @@ impl SolidFireParser {
     fn parse_basic_format(&self, line: &str, line_num: u32, captures: &regex::Captures) -> LogRecord {
         let timestamp_str = captures.get(1).unwrap().as_str();
         let (date, time) = self.parse_timestamp(timestamp_str);
+        let level = captures.get(5).unwrap().as_str();
+        let severity = Severity::from_level(level);
+        let source = captures.get(9).unwrap().as_str().trim();
+        let location = SourceLocation::parse(source);
         
         let content = captures.get(10).map(|m| m.as_str()).unwrap_or("");
         let dynamic_fields = self.parse_key_value_pairs(content);
         
         LogRecord {
             line_num,
+            input_file: None,
             date,
             time,
             timestamp: timestamp_str.to_string(),
             hostname: Some(captures.get(2).unwrap().as_str().to_string()),
             process: Some(captures.get(3).unwrap().as_str().trim().to_string()),
             pid: captures.get(4).unwrap().as_str().parse().ok(),
-            level: Some(captures.get(5).unwrap().as_str().to_string()),
+            level: Some(level.to_string()),
+            severity,
+            severity_num: severity.map(Severity::as_num),
             component: Some(captures.get(6).unwrap().as_str().to_string()),
             thread: captures.get(7).unwrap().as_str().parse().ok(),
             class: Some(captures.get(8).unwrap().as_str().to_string()),
-            source: Some(captures.get(9).unwrap().as_str().trim().to_string()),
+            source: Some(source.to_string()),
+            src_file: location.file,
+            src_line: location.line,
+            src_func: location.func,
+            subsystem: location.subsystem,
             content: Some(content.to_string()),
+            template_id: None,
+            template: None,
             raw_line: line.to_string(),
+            format: LogFormat::Basic,
             parse_error: None,
+            decode_error: None,
             dynamic_fields,
         }
     }
@@ -116,6 +335,10 @@ impl SolidFireParser {
     fn parse_call_format(&self, line: &str, line_num: u32, captures: &regex::Captures) -> LogRecord {
         let timestamp_str = captures.get(1).unwrap().as_str();
         let (date, time) = self.parse_timestamp(timestamp_str);
+        let level = captures.get(5).unwrap().as_str();
+        let severity = Severity::from_level(level);
+        let source = captures.get(9).unwrap().as_str().trim();
+        let location = SourceLocation::parse(source);
         
         let content = captures.get(11).map(|m| m.as_str()).unwrap_or("");
         let mut dynamic_fields = self.parse_key_value_pairs(content);
@@ -128,20 +351,31 @@ impl SolidFireParser {
         
         LogRecord {
             line_num,
+            input_file: None,
             date,
             time,
             timestamp: timestamp_str.to_string(),
             hostname: Some(captures.get(2).unwrap().as_str().to_string()),
             process: Some(captures.get(3).unwrap().as_str().trim().to_string()),
             pid: captures.get(4).unwrap().as_str().parse().ok(),
-            level: Some(captures.get(5).unwrap().as_str().to_string()),
+            level: Some(level.to_string()),
+            severity,
+            severity_num: severity.map(Severity::as_num),
             component: Some(captures.get(6).unwrap().as_str().to_string()),
             thread: captures.get(7).unwrap().as_str().parse().ok(),
             class: Some(captures.get(8).unwrap().as_str().to_string()),
-            source: Some(captures.get(9).unwrap().as_str().trim().to_string()),
+            source: Some(source.to_string()),
+            src_file: location.file,
+            src_line: location.line,
+            src_func: location.func,
+            subsystem: location.subsystem,
             content: Some(content.to_string()),
+            template_id: None,
+            template: None,
             raw_line: line.to_string(),
+            format: LogFormat::Call,
             parse_error: None,
+            decode_error: None,
             dynamic_fields,
         }
     }
@@ -150,31 +384,20 @@ impl SolidFireParser {
         let parts: Vec<&str> = line.split_whitespace().collect();
         
         if parts.len() < 3 {
-            return LogRecord {
-                line_num,
-                date: "".to_string(),
-                time: "".to_string(),
-                timestamp: "".to_string(),
-                hostname: None,
-                process: None,
-                pid: None,
-                level: None,
-                component: None,
-                thread: None,
-                class: None,
-                source: None,
-                content: Some(line.to_string()),
-                raw_line: line.to_string(),
-                parse_error: Some("Failed to parse - insufficient parts".to_string()),
-                dynamic_fields: HashMap::new(),
-            };
+            return LogRecord::unparsed(line, line_num);
         }
         
         let timestamp_str = parts[0];
         let (date, time) = self.parse_timestamp(timestamp_str);
+        let parse_error = if date.is_empty() {
+            ParseErrorCode::InvalidTimestamp
+        } else {
+            ParseErrorCode::HeaderMismatch
+        };
         
         LogRecord {
             line_num,
+            input_file: None,
             date,
             time,
             timestamp: timestamp_str.to_string(),
@@ -182,13 +405,23 @@ impl SolidFireParser {
             process: parts.get(2).map(|s| s.to_string()),
             pid: None,
             level: None,
+            severity: None,
+            severity_num: None,
             component: None,
             thread: None,
             class: None,
             source: None,
+            src_file: None,
+            src_line: None,
+            src_func: None,
+            subsystem: None,
             content: Some(parts[3..].join(" ")),
+            template_id: None,
+            template: None,
             raw_line: line.to_string(),
-            parse_error: Some("Minimal parsing used".to_string()),
+            format: LogFormat::Minimal,
+            parse_error: Some(parse_error),
+            decode_error: None,
             dynamic_fields: HashMap::new(),
         }
     }
@@ -233,97 +466,456 @@ impl SolidFireParser {
                     serde_json::Value::String(value.to_string())
                 };
                 
-                result.insert(key.to_string(), typed_value);
+                result.insert(dynamic_field_name(key).into_owned(), typed_value);
             }
         }
         
         result
     }
     
-    pub fn parse_file(&self, input_path: &Path, output_path: &Path, chunk_size: usize) -> Result<()> {
+    /// Parse `input_path` into `output_path`. A Ctrl-C is not an error: the
+    /// partial run's statistics come back with `interrupted` set.
+    pub fn parse_file(&self, input_path: &Path, output_path: &Path, options: &ParseOptions) -> Result<ParseStats> {
+        self.parse_files(&[input_path.to_path_buf()], output_path, options)
+    }
+    
+    /// Parse several inputs into one output, merged by timestamp. Line
+    /// numbers stay per input, and each record names its `input_file`.
+    pub fn parse_files(&self, input_paths: &[PathBuf], output_path: &Path, options: &ParseOptions) -> Result<ParseStats> {
         let start_time = Instant::now();
+        let merged = input_paths.len() > 1;
+        let input_names: Vec<String> = input_paths.iter()
+            .map(|path| if is_stdio(path) { "<stdin>".to_string() } else { path.display().to_string() })
+            .collect();
         
         // Display version and info
-        println!("🔥 SolidFire Log Parser v{}", env!("CARGO_PKG_VERSION"));
-        println!("📊 Parsing {} to {}", input_path.display(), output_path.display());
+        status!("🔥 SolidFire Log Parser v{}", env!("CARGO_PKG_VERSION"));
+        status!("📊 Parsing {} to {}",
+            input_names.join(", "),
+            if is_stdio(output_path) { "<stdout>".to_string() } else { output_path.display().to_string() });
+        
+        if merged {
+            if input_paths.iter().any(|path| is_stdio(path)) {
+                anyhow::bail!("stdin (-) can't be merged with other inputs");
+            }
+            if options.resume {
+                anyhow::bail!("--resume supports a single input file");
+            }
+        }
+        if options.resume && options.templates_path.is_some() {
+            anyhow::bail!("--templates can't be combined with --resume; template ids would start over");
+        }
+        if options.resume && options.dedup_window.is_some() {
+            anyhow::bail!("--dedup can't be combined with --resume; the duplicate window would start empty");
+        }
+        if options.split_by.is_some() {
+            if options.format == OutputFormat::Parquet {
+                anyhow::bail!("--split-by can't be combined with --format parquet, which is already partitioned by date and component");
+            }
+            if options.resume {
+                anyhow::bail!("--split-by can't be combined with --resume");
+            }
+        }
+        if options.overwrite && options.format != OutputFormat::Parquet && options.split_by.is_none() {
+            anyhow::bail!("--overwrite only applies to --format parquet and --split-by; other outputs are always replaced");
+        }
+        if (options.split_by.is_some() || options.format == OutputFormat::Parquet) && is_stdio(output_path) {
+            anyhow::bail!("Partitioned output is a directory of files; it can't go to stdout");
+        }
+        
+        let spooled_stdin;
+        let input_paths: Vec<&Path> = if is_stdio(&input_paths[0]) {
+            if options.resume {
+                anyhow::bail!("--resume needs a named input file, not stdin");
+            }
+            spooled_stdin = spool_stdin()?;
+            vec![spooled_stdin.path()]
+        } else {
+            input_paths.iter().map(PathBuf::as_path).collect()
+        };
+        
+        let mut inputs = Vec::new();
+        for (path, name) in input_paths.iter().zip(&input_names) {
+            let bytes = std::fs::metadata(path)
+                .with_context(|| format!("Failed to open input file: {}", path.display()))?
+                .len();
+            let (fingerprint, fingerprint_len) = input_fingerprint(path, bytes)?;
+            inputs.push(InputSummary {
+                path: name.clone(),
+                bytes: Some(bytes),
+                fingerprint: Some(fingerprint),
+                fingerprint_len: Some(fingerprint_len),
+            });
+        }
         
-        // Phase 1: Discover all possible dynamic fields
-        println!("Phase 1: Discovering schema...");
-        let all_dynamic_fields = self.discover_schema(input_path)?;
-        println!("Found {} dynamic fields", all_dynamic_fields.len());
+        // Checkpoints point into the output file, so stdout output has none,
+        // and they track a single input's offset, so merged runs have none.
+        // Split output is spread over many files, so it has none either
+        let checkpointed = options.format == OutputFormat::Ndjson
+            && !is_stdio(output_path)
+            && !merged
+            && options.split_by.is_none();
+        let checkpoint_path = Checkpoint::path_for(output_path);
+        let resume_from = if options.resume {
+            if !checkpointed {
+                anyhow::bail!("--resume needs appendable output; use --format ndjson with an output file");
+            }
+            let checkpoint = Checkpoint::load(&checkpoint_path)?;
+            checkpoint.validate(input_paths[0])?;
+            status!("Resuming from line {} (byte offset {})", checkpoint.line_num + 1, checkpoint.byte_offset);
+            Some(checkpoint)
+        } else {
+            None
+        };
+        
+        let (mut schema, total_lines) = match resume_from {
+            Some(ref checkpoint) => {
+                let fields = checkpoint.schema_fields.iter().cloned().collect();
+                (IncrementalSchema::with_fields(fields, checkpoint.schema_version), None)
+            }
+            None => {
+                // Phase 1: Discover all possible dynamic fields
+                status!("Phase 1: Discovering schema...");
+                let mut fields = HashSet::new();
+                let mut total_lines = 0;
+                for path in &input_paths {
+                    let (input_fields, input_lines) = self.discover_schema(path, options.max_line_bytes)?;
+                    fields.extend(input_fields);
+                    total_lines += input_lines;
+                }
+                status!("Found {} dynamic fields", fields.len());
+                (IncrementalSchema::with_fields(fields, 1), Some(total_lines))
+            }
+        };
         
         // Phase 2: Parse with consistent schema
-        println!("Phase 2: Parsing with consistent schema...");
+        status!("Phase 2: Parsing with consistent schema...");
         
-        let file = File::open(input_path)
-            .with_context(|| format!("Failed to open input file: {}", input_path.display()))?;
-        let reader = BufReader::new(file);
+        let (start_offset, start_line) = match resume_from {
+            Some(ref checkpoint) => (checkpoint.byte_offset, checkpoint.line_num),
+            None => (0, 0),
+        };
         
-        let mut output_file = File::create(output_path)
-            .with_context(|| format!("Failed to create output file: {}", output_path.display()))?;
+        let mut sources = Vec::new();
+        for path in &input_paths {
+            let mut file = File::open(path)
+                .with_context(|| format!("Failed to open input file: {}", path.display()))?;
+            file.seek(SeekFrom::Start(start_offset))?;
+            sources.push((LossyLines::new(BufReader::new(file), options.max_line_bytes), start_line));
+        }
+        let mut lines = MergedLines::new(sources);
+        let mut dedup = options.dedup_window.map(Deduplicator::new);
+        let mut miner = options.templates_path.as_ref().map(|_| TemplateMiner::default());
         
-        // Write JSON array start
-        writeln!(output_file, "[")?;
+        let mut writer = match (&resume_from, options.split_by) {
+            (Some(checkpoint), _) => RecordSink::Single(RecordWriter::append(output_path, checkpoint.output_offset)?),
+            (None, _) if options.format == OutputFormat::Parquet => RecordSink::Dataset(ParquetDataset::create(output_path, options.overwrite)?),
+            (None, Some(split_by)) => RecordSink::Split(SplitWriter::create(output_path, split_by, options.format, options.overwrite)?),
+            (None, None) if checkpointed => RecordSink::Single(RecordWriter::create_in_place(output_path)?),
+            (None, None) => RecordSink::Single(RecordWriter::create(output_path, options.format)?),
+        };
+        
+        // A resumed run cuts the rejects file back to the checkpoint, like
+        // the output, so lines after it aren't listed twice
+        let mut rejects_bytes = 0;
+        let mut rejects_file = match options.rejects_path {
+            Some(ref rejects_path) => {
+                if let Some(ref checkpoint) = resume_from {
+                    rejects_bytes = checkpoint.rejects_offset.with_context(|| format!(
+                        "--rejects {} can't be resumed; the checkpointed run didn't use --rejects",
+                        rejects_path.display()
+                    ))?;
+                }
+                let mut file = OpenOptions::new()
+                    .create(true)
+                    .write(true)
+                    .truncate(!options.resume)
+                    .open(rejects_path)
+                    .with_context(|| format!("Failed to create rejects file: {}", rejects_path.display()))?;
+                if options.resume {
+                    file.set_len(rejects_bytes)
+                        .with_context(|| format!("Failed to truncate rejects file: {}", rejects_path.display()))?;
+                    file.seek(SeekFrom::End(0))?;
+                }
+                Some(BufWriter::new(file))
+            }
+            None => None,
+        };
         
-        let mut line_num = 1u32;
-        let mut first_record = true;
         let mut processed_lines = 0;
+        let mut format_counts = FormatCounts::default();
+        let mut failure_shapes = FailureShapes::default();
+        let mut rejected_lines = 0;
+        let mut decode_errors = 0;
+        let mut time_span = TimeSpan::default();
+        let mut interrupted = false;
         
         // Process in chunks for memory efficiency and progress reporting
-        let lines: Vec<String> = reader.lines().collect::<Result<Vec<_>, _>>()?;
-        let total_lines = lines.len();
-        
-        for chunk in lines.chunks(chunk_size) {
+        loop {
+            let chunk: Vec<InputLine> = lines.by_ref()
+                .take(options.chunk_size)
+                .collect::<Result<Vec<_>, _>>()?;
+            if chunk.is_empty() {
+                break;
+            }
+            
+            if let Some(ref redactor) = self.redactor {
+                redactor.learn(chunk.par_iter().map(|input| input.line.text.as_str()));
+            }
+            
+            let schema_version = schema.version();
             let records: Vec<LogRecord> = chunk
                 .par_iter()
-                .enumerate()
-                .map(|(i, line)| {
-                    let mut record = self.parse_line(line, line_num + i as u32);
+                .map(|input| {
+                    let mut record = self.parse_line(&input.line.text, input.line_num);
+                    record.decode_error = input.line.decode_error;
+                    if merged {
+                        record.input_file = Some(input_names[input.input].clone());
+                    }
                     // Ensure all dynamic fields exist with null values if missing
-                    self.ensure_complete_schema(&mut record, &all_dynamic_fields);
+                    self.ensure_complete_schema(&mut record, schema.fields());
                     record
                 })
                 .collect();
             
-            // Write records to JSON
-            for record in records {
-                if !first_record {
-                    writeln!(output_file, ",")?;
+            // A trailing line without a newline may still be growing; the
+            // checkpoint stops before it so a resume re-reads it in full
+            let complete_lines = start_line + lines.complete_lines() as u32;
+            let mut checkpoint_output_offset = writer.bytes_written();
+            let mut checkpoint_rejects_offset = rejects_bytes;
+            
+            for mut record in records {
+                if let Some(ref mut dedup) = dedup {
+                    if dedup.is_duplicate(&record) {
+                        continue;
+                    }
+                }
+                if let Some(ref mut miner) = miner {
+                    let content = record.content.as_deref().unwrap_or(&record.raw_line);
+                    let (template_id, template) = miner.observe(content, &record.timestamp);
+                    record.template_id = Some(template_id);
+                    record.template = Some(template);
+                }
+                
+                schema.observe(&record);
+                if schema.version() != schema_version {
+                    self.ensure_complete_schema(&mut record, schema.fields());
+                }
+                
+                format_counts.record(record.format);
+                time_span.observe(&record.timestamp);
+                if record.decode_error.is_some() {
+                    decode_errors += 1;
+                }
+                if record.parse_error.is_some() {
+                    failure_shapes.record(&record);
+                    if let Some(ref mut rejects) = rejects_file {
+                        let reject = format!("{}\t{}\n", record.line_num, record.raw_line);
+                        rejects.write_all(reject.as_bytes())?;
+                        rejects_bytes += reject.len() as u64;
+                        rejected_lines += 1;
+                    }
+                }
+                
+                writer.write_record(&record)?;
+                if record.line_num <= complete_lines {
+                    checkpoint_output_offset = writer.bytes_written();
+                    checkpoint_rejects_offset = rejects_bytes;
                 }
-                let json = serde_json::to_string(&record)?;
-                write!(output_file, "  {}", json)?;
-                first_record = false;
             }
             
-            line_num += chunk.len() as u32;
             processed_lines += chunk.len();
             
+            if checkpointed {
+                writer.flush()?;
+                if let Some(ref mut rejects) = rejects_file {
+                    rejects.flush()?;
+                }
+                let byte_offset = start_offset + lines.complete_offset();
+                let (input_fingerprint, fingerprint_len) = input_fingerprint(input_paths[0], byte_offset)?;
+                let mut schema_fields: Vec<String> = schema.fields().iter().cloned().collect();
+                schema_fields.sort();
+                Checkpoint {
+                    parser_version: env!("CARGO_PKG_VERSION").to_string(),
+                    input_path: input_paths[0].display().to_string(),
+                    input_fingerprint,
+                    fingerprint_len,
+                    byte_offset,
+                    line_num: complete_lines,
+                    output_offset: checkpoint_output_offset,
+                    rejects_offset: rejects_file.as_ref().map(|_| checkpoint_rejects_offset),
+                    schema_version: schema.version(),
+                    schema_fields,
+                }.save(&checkpoint_path)?;
+            }
+            
             if processed_lines % 10000 == 0 {
-                println!("Processed {} / {} lines ({:.1}%)", 
-                    processed_lines, total_lines, 
-                    (processed_lines as f64 / total_lines as f64) * 100.0);
+                match total_lines {
+                    Some(total_lines) => status!("Processed {} / {} lines ({:.1}%)", 
+                        processed_lines, total_lines, 
+                        (processed_lines as f64 / total_lines as f64) * 100.0),
+                    None => status!("Processed {} lines", processed_lines),
+                }
             }
+            
+            if interrupt::requested(&options.interrupt) {
+                interrupted = true;
+                break;
+            }
+        }
+        
+        if interrupted {
+            if let Some(mut rejects) = rejects_file {
+                rejects.flush()?;
+            }
+            if checkpointed {
+                writer.flush()?;
+                status!("Partial output is valid up to the checkpoint; continue with --resume");
+            } else if options.keep_partial {
+                let records = writer.records();
+                writer.finish()?;
+                status!("Kept partial output with {} records in {}", records, output_path.display());
+            } else {
+                // Dropping the writer discards the temporary files (or the
+                // Parquet spool)
+                drop(writer);
+            }
+        } else {
+            writer.finish()?;
+            if let Some(mut rejects) = rejects_file {
+                rejects.flush()?;
+            }
+        }
+        
+        if let (Some(miner), Some(templates_path)) = (&miner, &options.templates_path) {
+            miner.write_catalog(templates_path)?;
+        }
+        
+        let stats = ParseStats {
+            inputs,
+            total_lines: processed_lines,
+            dynamic_field_count: schema.len(),
+            format_counts,
+            failure_shapes,
+            rejected_lines,
+            decode_errors,
+            duplicates_dropped: dedup.map_or(0, |dedup| dedup.dropped()),
+            template_count: miner.as_ref().map_or(0, TemplateMiner::len),
+            renamed_fields: renamed_fields(schema.fields()),
+            time_span,
+            duration: start_time.elapsed(),
+            interrupted,
+        };
+        if interrupted {
+            return Ok(stats);
+        }
+        
+        let lines_per_sec = processed_lines as f64 / stats.duration.as_secs_f64();
+        
+        status!("Completed: {} lines in {:.2?} ({:.0} lines/sec)", 
+            processed_lines, stats.duration, lines_per_sec);
+        status!("All records now have consistent columns!");
+        status!("{}", quality_report(&stats.format_counts, &stats.failure_shapes).trim_end());
+        if stats.decode_errors > 0 {
+            status!("Recovered {} lines with decode errors (marked in decode_error)", stats.decode_errors);
+        }
+        if !stats.renamed_fields.is_empty() {
+            status!("Renamed keys that clash with fixed columns: {}", stats.renamed_fields.join(", "));
+        }
+        if options.dedup_window.is_some() {
+            status!("Dropped {} duplicate records", stats.duplicates_dropped);
+        }
+        if let Some(ref templates_path) = options.templates_path {
+            status!("Mined {} message templates into {}", stats.template_count, templates_path.display());
+        }
+        if let Some(ref rejects_path) = options.rejects_path {
+            status!("Wrote {} rejected lines to {}", stats.rejected_lines, rejects_path.display());
+        }
+        if checkpointed {
+            status!("Checkpoint saved to: {} (continue later with --resume)", checkpoint_path.display());
         }
         
-        // Write JSON array end
-        writeln!(output_file, "\n]")?;
+        Ok(stats)
+    }
+    
+    /// Tail `input_path` and stream parsed records as NDJSON to `output_path`,
+    /// or to stdout when no output is given. Runs until interrupted. An
+    /// existing output (and rejects) file is appended to, so restarting a
+    /// follow doesn't wipe what the last one collected.
+    pub fn follow_file(&self, input_path: &Path, output_path: Option<&Path>, options: &ParseOptions, from_start: bool) -> Result<()> {
+        status!("🔥 SolidFire Log Parser v{}", env!("CARGO_PKG_VERSION"));
+        status!("👀 Following {} (Ctrl-C to stop)", input_path.display());
+        
+        let mut output: Box<dyn Write> = match output_path {
+            Some(path) => Box::new(BufWriter::new(OpenOptions::new().create(true).append(true).open(path)
+                .with_context(|| format!("Failed to create output file: {}", path.display()))?)),
+            None => Box::new(BufWriter::new(std::io::stdout().lock())),
+        };
         
-        let duration = start_time.elapsed();
-        let lines_per_sec = total_lines as f64 / duration.as_secs_f64();
+        let mut rejects_file = match options.rejects_path {
+            Some(ref rejects_path) => Some(BufWriter::new(OpenOptions::new().create(true).append(true).open(rejects_path)
+                .with_context(|| format!("Failed to create rejects file: {}", rejects_path.display()))?)),
+            None => None,
+        };
         
-        println!("Completed: {} lines in {:.2?} ({:.0} lines/sec)", 
-            total_lines, duration, lines_per_sec);
-        println!("All records now have consistent columns!");
+        let mut follower = LogFollower::new(input_path, options.max_line_bytes, from_start);
+        let mut schema = IncrementalSchema::default();
+        let mut dedup = options.dedup_window.map(Deduplicator::new);
         
-        Ok(())
+        loop {
+            if interrupt::requested(&options.interrupt) {
+                output.flush()?;
+                if let Some(ref mut rejects) = rejects_file {
+                    rejects.flush()?;
+                }
+                return Ok(());
+            }
+            
+            let lines = follower.poll()
+                .with_context(|| format!("Failed to read {}", follower.path().display()))?;
+            if lines.is_empty() {
+                std::thread::sleep(POLL_INTERVAL);
+                continue;
+            }
+            
+            for (line_num, line) in lines {
+                let mut record = self.parse_line(&line.text, line_num);
+                record.decode_error = line.decode_error;
+                if let Some(ref mut dedup) = dedup {
+                    if dedup.is_duplicate(&record) {
+                        continue;
+                    }
+                }
+                let new_fields = schema.observe(&record);
+                if new_fields > 0 {
+                    status!("Schema grew by {} fields to {}", new_fields, schema.len());
+                }
+                self.ensure_complete_schema(&mut record, schema.fields());
+                if record.parse_error.is_some() {
+                    if let Some(ref mut rejects) = rejects_file {
+                        writeln!(rejects, "{}\t{}", record.line_num, record.raw_line)?;
+                    }
+                }
+                serde_json::to_writer(&mut output, &record)?;
+                writeln!(output)?;
+            }
+            output.flush()?;
+            if let Some(ref mut rejects) = rejects_file {
+                rejects.flush()?;
+            }
+        }
     }
     
-    fn discover_schema(&self, input_path: &Path) -> Result<HashSet<String>> {
+    /// Sample the input for dynamic field names. Returns the fields and the
+    /// total number of lines.
+    fn discover_schema(&self, input_path: &Path, max_line_bytes: usize) -> Result<(HashSet<String>, usize)> {
         let file = File::open(input_path)?;
         let reader = BufReader::new(file);
         
         let mut all_fields = HashSet::new();
-        let lines: Vec<String> = reader.lines().collect::<Result<Vec<_>, _>>()?;
+        let lines: Vec<String> = LossyLines::new(reader, max_line_bytes)
+            .map(|line| line.map(|line| line.text))
+            .collect::<Result<Vec<_>, _>>()?;
         let total_lines = lines.len();
         
         // Sample strategically: first 1000, middle 1000, last 1000, plus every 1000th line
@@ -356,7 +948,7 @@ impl SolidFireParser {
             }
         }
         
-        println!("Sampling {} lines from {} total lines for comprehensive schema discovery", 
+        status!("Sampling {} lines from {} total lines for comprehensive schema discovery", 
             sample_indices.len(), total_lines);
         
         for &i in &sample_indices {
@@ -364,11 +956,12 @@ impl SolidFireParser {
             all_fields.extend(fields);
         }
         
-        Ok(all_fields)
+        Ok((all_fields, total_lines))
     }
     
     fn extract_dynamic_field_names(&self, line: &str) -> HashSet<String> {
         let mut fields = HashSet::new();
+        let line = self.redact(line);
         
         // Extract content part (after the | separator)
         if let Some(pipe_pos) = line.find('|') {
@@ -381,7 +974,7 @@ impl SolidFireParser {
                     // Skip complex nested structures for consistency
                     let value = &word[eq_pos + 1..];
                     if !value.contains('{') && !value.contains('[') {
-                        fields.insert(key.to_string());
+                        fields.insert(dynamic_field_name(key).into_owned());
                     }
                 }
             }
@@ -400,16 +993,30 @@ impl SolidFireParser {
     }
 }
 
-fn main() -> Result<()> {
+fn main() {
+    let code = match run() {
+        Ok(code) => code,
+        Err(e) if is_broken_pipe(&e) => EXIT_OK,
+        Err(e) => {
+            eprintln!("Error: {:?}", e);
+            EXIT_FAILURE
+        }
+    };
+    std::process::exit(code);
+}
+
+/// Run the CLI and return the process exit code.
+fn run() -> Result<i32> {
     let matches = Command::new("sf-parser")
         .version(env!("CARGO_PKG_VERSION"))
         .about("High-performance SolidFire log parser with nushell compatibility")
         .arg(Arg::new("input")
-            .help("Input log file")
+            .help("Input log file(s), or - for stdin; several files are merged by timestamp")
             .required(true)
+            .num_args(1..)
             .index(1))
         .arg(Arg::new("output")
-            .help("Output JSON file")
+            .help("Output JSON file, or - for stdout (default for stdin input)")
             .short('o')
             .long("output"))
         .arg(Arg::new("chunk-size")
@@ -417,14 +1024,119 @@ fn main() -> Result<()> {
             .short('c')
             .long("chunk-size")
             .default_value("1000"))
+        .arg(Arg::new("rejects")
+            .help("Write lines that fail to parse to this file (line_num<TAB>line)")
+            .long("rejects")
+            .value_name("FILE"))
+        .arg(Arg::new("max-line-length")
+            .help("Truncate lines longer than this many bytes")
+            .long("max-line-length")
+            .value_name("BYTES")
+            .default_value("1048576"))
+        .arg(Arg::new("max-error-rate")
+            .help("Fail the run if more than this fraction of lines fail to parse (e.g. 0.01 or 1%)")
+            .long("max-error-rate")
+            .value_name("RATE")
+            .conflicts_with("follow"))
+        .arg(Arg::new("format")
+            .help("Output format: json (single array), ndjson (one record per line, resumable) or parquet (Hive-partitioned dataset directory)")
+            .long("format")
+            .value_name("FORMAT")
+            .default_value("json"))
+        .arg(Arg::new("resume")
+            .help("Continue an NDJSON parse from its checkpoint, appending only new records")
+            .long("resume")
+            .action(clap::ArgAction::SetTrue)
+            .conflicts_with("follow"))
+        .arg(Arg::new("keep-partial")
+            .help("If interrupted, keep the output written so far (closed into a valid document) instead of discarding it")
+            .long("keep-partial")
+            .action(clap::ArgAction::SetTrue)
+            .conflicts_with("follow"))
+        .arg(Arg::new("overwrite")
+            .help("Replace the Parquet dataset or split output already in the output directory")
+            .long("overwrite")
+            .action(clap::ArgAction::SetTrue))
+        .arg(Arg::new("follow")
+            .help("Tail the input like tail -F and stream records as NDJSON (stdout unless -o is given)")
+            .short('f')
+            .long("follow")
+            .action(clap::ArgAction::SetTrue))
+        .arg(Arg::new("from-start")
+            .help("With --follow, emit the existing contents before new lines")
+            .long("from-start")
+            .action(clap::ArgAction::SetTrue))
+        .arg(Arg::new("dedup")
+            .help("Drop duplicate records (same timestamp, host, pid, thread and content)")
+            .long("dedup")
+            .action(clap::ArgAction::SetTrue))
+        .arg(Arg::new("dedup-window")
+            .help("How far apart in time duplicates may be (e.g. 30s, 5m)")
+            .long("dedup-window")
+            .value_name("DURATION")
+            .default_value("60s"))
+        .arg(Arg::new("templates")
+            .help("Mine message templates into template_id/template columns and write their catalog to FILE")
+            .long("templates")
+            .value_name("FILE")
+            .conflicts_with("follow"))
+        .arg(Arg::new("split-by")
+            .help("Write one file per component, hostname, hour or day into the output directory, plus a manifest")
+            .long("split-by")
+            .value_name("FIELD")
+            .conflicts_with("follow"))
+        .arg(Arg::new("redact")
+            .help("Replace IPs, usernames, hostnames, serials and UUIDs with keyed-hash pseudonyms")
+            .long("redact")
+            .action(clap::ArgAction::SetTrue))
+        .arg(Arg::new("redact-rules")
+            .help("TOML file of redaction rules (implies --redact)")
+            .long("redact-rules")
+            .value_name("FILE"))
+        .arg(Arg::new("redact-key-file")
+            .help("File holding the pseudonym key (default: $SF_REDACT_KEY)")
+            .long("redact-key-file")
+            .value_name("FILE"))
+        .arg(Arg::new("summary-json")
+            .help("Write a machine-readable run summary to this file")
+            .long("summary-json")
+            .value_name("FILE")
+            .conflicts_with("follow"))
+        .arg(Arg::new("quiet")
+            .help("Suppress progress and summary messages on stderr")
+            .short('q')
+            .long("quiet")
+            .action(clap::ArgAction::SetTrue))
         .get_matches();
     
-    let input_path = Path::new(matches.get_one::<String>("input").unwrap());
+    QUIET.store(matches.get_flag("quiet"), std::sync::atomic::Ordering::Relaxed);
+    
+    let input_paths: Vec<PathBuf> = matches.get_many::<String>("input")
+        .unwrap()
+        .map(PathBuf::from)
+        .collect();
+    let input_path = input_paths[0].as_path();
+    let follow = matches.get_flag("follow");
+    let format_name = matches.get_one::<String>("format").unwrap();
+    let format = OutputFormat::from_name(format_name)
+        .with_context(|| format!("Invalid output format: {} (expected json, ndjson or parquet)", format_name))?;
+    let split_by = match matches.get_one::<String>("split-by") {
+        Some(name) => Some(SplitBy::from_name(name)
+            .with_context(|| format!("Invalid split field: {} (expected component, hostname, hour or day)", name))?),
+        None => None,
+    };
     let output_path = if let Some(output) = matches.get_one::<String>("output") {
         Path::new(output).to_path_buf()
+    } else if is_stdio(input_path) {
+        PathBuf::from("-")
     } else {
         let mut output = input_path.to_path_buf();
-        output.set_extension("parsed.json");
+        match (split_by, format) {
+            (Some(_), _) => output.set_extension("parsed"),
+            (None, OutputFormat::Json) => output.set_extension("parsed.json"),
+            (None, OutputFormat::Ndjson) => output.set_extension("parsed.ndjson"),
+            (None, OutputFormat::Parquet) => output.set_extension("parquet"),
+        };
         output
     };
     
@@ -433,20 +1145,122 @@ fn main() -> Result<()> {
         .parse()
         .context("Invalid chunk size")?;
     
-    let parser = SolidFireParser::new()?;
-    parser.parse_file(input_path, &output_path, chunk_size)?;
+    let max_error_rate = match matches.get_one::<String>("max-error-rate") {
+        Some(rate) => Some(parse_rate(rate)?),
+        None => None,
+    };
+    
+    let max_line_bytes: usize = matches.get_one::<String>("max-line-length")
+        .unwrap()
+        .parse()
+        .context("Invalid max line length")?;
+    
+    let dedup_window = if matches.get_flag("dedup") {
+        Some(parse_duration(matches.get_one::<String>("dedup-window").unwrap())?)
+    } else {
+        None
+    };
+    
+    let options = ParseOptions {
+        chunk_size,
+        rejects_path: matches.get_one::<String>("rejects").map(PathBuf::from),
+        max_line_bytes,
+        format,
+        resume: matches.get_flag("resume"),
+        keep_partial: matches.get_flag("keep-partial"),
+        overwrite: matches.get_flag("overwrite"),
+        dedup_window,
+        templates_path: matches.get_one::<String>("templates").map(PathBuf::from),
+        split_by,
+        interrupt: Some(interrupt::install()?),
+    };
+    
+    let mut parser = SolidFireParser::new()?;
+    let redact_rules = matches.get_one::<String>("redact-rules").map(Path::new);
+    if matches.get_flag("redact") || redact_rules.is_some() {
+        let rules = match redact_rules {
+            Some(path) => RedactRule::load(path)?,
+            None => RedactRule::defaults(),
+        };
+        let key = Redactor::load_key(matches.get_one::<String>("redact-key-file").map(Path::new))?;
+        parser = parser.with_redactor(Redactor::new(&key, rules)?);
+    }
+    
+    if follow {
+        if input_paths.len() > 1 {
+            anyhow::bail!("--follow takes a single input file");
+        }
+        if is_stdio(input_path) {
+            anyhow::bail!("--follow needs a named input file, not stdin");
+        }
+        if format == OutputFormat::Parquet {
+            anyhow::bail!("--follow streams NDJSON; use --format parquet on a finished log instead");
+        }
+        let output = matches.get_one::<String>("output")
+            .map(Path::new)
+            .filter(|path| !is_stdio(path));
+        parser.follow_file(input_path, output, &options, matches.get_flag("from-start"))?;
+        return Ok(EXIT_OK);
+    }
+    
+    let summary_path = matches.get_one::<String>("summary-json").map(PathBuf::from);
+    
+    let stats = match parser.parse_files(&input_paths, &output_path, &options) {
+        Ok(stats) => stats,
+        Err(e) => {
+            if let Some(ref summary_path) = summary_path {
+                RunSummary::new(&input_paths, &output_path, RunStatus::Failed)
+                    .with_error(format!("{:#}", e))
+                    .write(summary_path)?;
+            }
+            return Err(e);
+        }
+    };
+    
+    let mut error = None;
+    let status = if stats.interrupted {
+        error = Some(format!("Interrupted after {} lines", stats.total_lines));
+        RunStatus::Interrupted
+    } else {
+        match max_error_rate {
+            Some(max_error_rate) if stats.format_counts.error_rate() > max_error_rate => {
+                error = Some(format!("Parse error rate {:.2}% exceeds maximum {:.2}% ({} of {} lines failed)",
+                    stats.format_counts.error_rate() * 100.0, max_error_rate * 100.0,
+                    stats.format_counts.failed(), stats.total_lines));
+                RunStatus::ErrorRateExceeded
+            }
+            _ => RunStatus::Ok,
+        }
+    };
+    
+    if let Some(ref summary_path) = summary_path {
+        let mut summary = RunSummary::from_stats(&output_path, status, &stats);
+        summary.error = error.clone();
+        summary.write(summary_path)?;
+    }
+    
+    if let Some(error) = error {
+        eprintln!("Error: {}", error);
+        return Ok(status.exit_code());
+    }
     
-    println!("Output saved to: {}", output_path.display());
-    println!("Usage: nu -c 'open {} | where serviceID == 230'", output_path.display());
+    if format == OutputFormat::Parquet {
+        status!("Dataset saved to: {}", output_path.display());
+        status!("Usage: duckdb -c \"SELECT component, count(*) FROM read_parquet('{}/**/*.parquet', hive_partitioning = true) GROUP BY ALL\"", output_path.display());
+    } else if split_by.is_some() {
+        status!("Partitions saved to: {} (see {})", output_path.display(), MANIFEST_FILE);
+    } else if !is_stdio(&output_path) {
+        status!("Output saved to: {}", output_path.display());
+        status!("Usage: nu -c 'open {} | where serviceID == 230'", output_path.display());
+    }
     
-    Ok(())
+    Ok(EXIT_OK)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::fs;
-    use std::path::PathBuf;
     use tempfile::TempDir;
     
     #[test]
@@ -481,12 +1295,91 @@ mod tests {
         assert_eq!(record.dynamic_fields.get("overrideSnapMirrorHold").unwrap(), &serde_json::Value::Bool(false));
     }
     
+    #[test]
+    fn test_level_normalization() {
+        let parser = SolidFireParser::new().unwrap();
+        let line = "2025-06-05T00:20:07.858372Z icpbasi03037 master-1[112875]: [APP-5] [MS] 2069182 BSDirector ms/ClusterStatistics.cpp:1452:GetBlockDriveUsageFromStats| serviceID=230 usedBytes=1909106990888";
+        
+        let record = parser.parse_line(line, 1);
+        
+        assert_eq!(record.level, Some("APP-5".to_string()));
+        assert_eq!(record.severity, Some(Severity::Notice));
+        assert_eq!(record.severity_num, Some(2));
+        
+        let json = serde_json::to_value(&record).unwrap();
+        assert_eq!(json["severity"], "notice");
+        assert_eq!(json["severity_num"], 2);
+    }
+    
+    #[test]
+    fn test_reserved_field_names() {
+        let parser = SolidFireParser::new().unwrap();
+        let line = "2025-06-12T09:08:35.989483Z icpbasi03037 master-1[112875]: [APP-5] [MS] 2069202 CFaultMon ms/ClusterFaultMonitor.cpp:3465:ProcessNewFaults|Detected new clusterFault={id=680 type=Service severity=Error code=BlockServiceUnhealthy nodeID=7 driveIDs={72} serviceID=123 resolved=false}";
+        
+        let record = parser.parse_line(line, 1);
+        assert_eq!(record.severity, Some(Severity::Notice));
+        assert_eq!(record.dynamic_fields.get("sf_severity"), Some(&serde_json::json!("Error")));
+        assert!(!record.dynamic_fields.contains_key("severity"));
+        
+        let fields = parser.extract_dynamic_field_names(line);
+        assert!(fields.contains("sf_severity") && !fields.contains("severity"));
+        
+        let json = serde_json::to_string(&record).unwrap();
+        assert_eq!(json.matches("\"severity\":").count(), 1);
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["severity"], "notice");
+        
+        // Every fixed column is reserved, so no key can be written twice
+        assert!(reserved_fields().contains("decode_error") && reserved_fields().contains("template_id"));
+        assert!(!reserved_fields().contains("serviceID"));
+        let fields: HashSet<String> = ["sf_severity", "sf_custom", "serviceID"].iter().map(|f| f.to_string()).collect();
+        assert_eq!(renamed_fields(&fields), vec!["sf_severity".to_string()]);
+    }
+    
+    #[test]
+    fn test_source_split() {
+        let parser = SolidFireParser::new().unwrap();
+        let line = "2025-06-05T00:20:07.858372Z icpbasi03037 master-1[112875]: [APP-5] [MS] 2069182 BSDirector ms/ClusterStatistics.cpp:1452:GetBlockDriveUsageFromStats| serviceID=230 usedBytes=1909106990888";
+        
+        let record = parser.parse_line(line, 1);
+        
+        assert_eq!(record.src_file, Some("ms/ClusterStatistics.cpp".to_string()));
+        assert_eq!(record.src_line, Some(1452));
+        assert_eq!(record.src_func, Some("GetBlockDriveUsageFromStats".to_string()));
+        assert_eq!(record.subsystem, Some("ms".to_string()));
+    }
+    
+    #[test]
+    fn test_parse_path_tracking() {
+        let parser = SolidFireParser::new().unwrap();
+        
+        let basic = parser.parse_line("2025-06-05T00:20:07.858372Z icpbasi03037 master-1[112875]: [APP-5] [MS] 2069182 BSDirector ms/ClusterStatistics.cpp:1452:GetBlockDriveUsageFromStats| serviceID=230", 1);
+        assert_eq!(basic.format, LogFormat::Basic);
+        assert_eq!(basic.parse_error, None);
+        
+        let minimal = parser.parse_line("2025-06-05T00:20:07.858372Z icpbasi03037 kernel: link down", 2);
+        assert_eq!(minimal.format, LogFormat::Minimal);
+        assert_eq!(minimal.parse_error, Some(ParseErrorCode::HeaderMismatch));
+        
+        let bad_timestamp = parser.parse_line("Jun 5 00:20:07 icpbasi03037 kernel: link down", 3);
+        assert_eq!(bad_timestamp.format, LogFormat::Minimal);
+        assert_eq!(bad_timestamp.parse_error, Some(ParseErrorCode::InvalidTimestamp));
+        
+        let unparsed = parser.parse_line("garbage", 4);
+        assert_eq!(unparsed.format, LogFormat::Unparsed);
+        assert_eq!(unparsed.parse_error, Some(ParseErrorCode::InsufficientParts));
+        
+        let json = serde_json::to_value(&unparsed).unwrap();
+        assert_eq!(json["format"], "unparsed");
+        assert_eq!(json["parse_error"], "insufficient_parts");
+    }
+    
     #[test]
     fn test_schema_consistency() {
         let parser = SolidFireParser::new().unwrap();
         
         // Create test data with different fields in each line
-        let lines = vec![
+        let lines = [
             "2025-06-05T00:20:07.858372Z icpbasi03037 master-1[112875]: [APP-5] [MS] 2069182 BSDirector ms/ClusterStatistics.cpp:1452:GetBlockDriveUsageFromStats| serviceID=230 usedBytes=1909106990888",
             "2025-06-12T08:35:00.177183Z icpbasi03037 master-1[112875]: [APP-5] [Vvols] 2069183 Scheduler cs/CServiceSliceSnapshots.cpp:1037:UnregisterSnapshot| snapshotID=13846639 vvolParms=<empty> overrideSnapMirrorHold=False",
             "2025-06-05T00:20:07.858372Z icpbasi03037 master-1[112875]: [APP-5] [MS] 2069182 BSDirector ms/ClusterStatistics.cpp:1452:GetBlockDriveUsageFromStats| volumeID=1207 nodeID=2"
@@ -503,8 +1396,8 @@ mod tests {
         }
         
         // Ensure all records have all dynamic fields (with nulls for missing)
-        for mut record in records.iter_mut() {
-            parser.ensure_complete_schema(&mut record.clone(), &all_fields);
+        for record in records.iter_mut() {
+            parser.ensure_complete_schema(record, &all_fields);
         }
         
         // Verify all records have the same number of dynamic fields
@@ -521,6 +1414,395 @@ mod tests {
         }
     }
     
+    #[test]
+    fn test_rejects_and_quality_report() {
+        let parser = SolidFireParser::new().unwrap();
+        let temp_dir = TempDir::new().unwrap();
+        
+        let test_log_path = temp_dir.path().join("test.log");
+        let test_content = "2025-06-05T00:20:07.858372Z icpbasi03037 master-1[112875]: [APP-5] [MS] 2069182 BSDirector ms/ClusterStatistics.cpp:1452:GetBlockDriveUsageFromStats| serviceID=230\n\
+Jun 5 00:20:07 icpbasi03037 kernel: link down\n\
+Jun 6 10:21:17 icpbasi03038 kernel: link up\n\
+garbage\n";
+        fs::write(&test_log_path, test_content).unwrap();
+        
+        let options = ParseOptions {
+            rejects_path: Some(temp_dir.path().join("rejects.txt")),
+            ..ParseOptions::default()
+        };
+        let stats = parser.parse_file(&test_log_path, &temp_dir.path().join("output.json"), &options).unwrap();
+        
+        assert_eq!(stats.rejected_lines, 3);
+        assert_eq!(stats.format_counts.failed(), 3);
+        assert!((stats.format_counts.error_rate() - 0.75).abs() < f64::EPSILON);
+        
+        let rejects = fs::read_to_string(temp_dir.path().join("rejects.txt")).unwrap();
+        assert_eq!(rejects, "2\tJun 5 00:20:07 icpbasi03037 kernel: link down\n\
+3\tJun 6 10:21:17 icpbasi03038 kernel: link up\n\
+4\tgarbage\n");
+        
+        // Both syslog-style lines share one shape; the sample is the first occurrence
+        let top = stats.failure_shapes.top(20);
+        assert_eq!(top.len(), 2);
+        assert_eq!(top[0].count, 2);
+        assert_eq!(top[0].sample_line_num, 2);
+        assert_eq!(top[0].shape, "invalid_timestamp: a # #:#:# a# a: a a");
+    }
+    
+    #[test]
+    fn test_lossy_decoding() {
+        let parser = SolidFireParser::new().unwrap();
+        let temp_dir = TempDir::new().unwrap();
+        
+        let test_log_path = temp_dir.path().join("corrupt.log");
+        let mut content = b"2025-06-05T00:20:07.858372Z icpbasi03037 master-1[112875]: [APP-5] [MS] 2069182 BSDirector ms/ClusterStatistics.cpp:1452:GetBlockDriveUsageFromStats| serviceID=230\n".to_vec();
+        content.extend_from_slice(b"\0\0\0\0\0\0\0\0\n");
+        content.extend_from_slice(b"2025-06-05T00:20:08.000000Z icpbasi03037 master-1[112875]: [APP-5] [MS] 2069182 BSDirector ms/ClusterStatistics.cpp:1452:GetBlockDriveUsageFromStats| name=caf\xc3\n");
+        content.extend_from_slice(&[b'x'; 300]);
+        content.extend_from_slice(b"\r\n");
+        fs::write(&test_log_path, &content).unwrap();
+        
+        let options = ParseOptions {
+            max_line_bytes: 256,
+            ..ParseOptions::default()
+        };
+        let output_path = temp_dir.path().join("output.json");
+        let stats = parser.parse_file(&test_log_path, &output_path, &options).unwrap();
+        assert_eq!(stats.total_lines, 4);
+        assert_eq!(stats.decode_errors, 3);
+        
+        let records: Vec<serde_json::Value> = serde_json::from_str(&fs::read_to_string(&output_path).unwrap()).unwrap();
+        assert_eq!(records[0]["decode_error"], serde_json::Value::Null);
+        assert_eq!(records[1]["decode_error"], "nul_bytes");
+        assert_eq!(records[1]["raw_line"], "");
+        assert_eq!(records[2]["decode_error"], "invalid_utf8");
+        assert_eq!(records[2]["name"], "caf\u{fffd}");
+        assert_eq!(records[3]["decode_error"], "line_truncated");
+        assert_eq!(records[3]["raw_line"].as_str().unwrap().len(), 256);
+    }
+    
+    #[test]
+    fn test_checkpoint_resume() {
+        let parser = SolidFireParser::new().unwrap();
+        let temp_dir = TempDir::new().unwrap();
+        let log_path = temp_dir.path().join("sf-master.info");
+        let output_path = temp_dir.path().join("output.ndjson");
+        let line = |n: u32| format!("2025-06-05T00:20:{:02}.858372Z icpbasi03037 master-1[112875]: [APP-5] [MS] 2069182 BSDirector ms/ClusterStatistics.cpp:1452:GetBlockDriveUsageFromStats| serviceID={}", n, n);
+        
+        // Three complete lines plus one that is still being written
+        fs::write(&log_path, format!("{}\n{}\n{}\n{}", line(1), line(2), line(3), &line(4)[..30])).unwrap();
+        let mut options = ParseOptions {
+            chunk_size: 2,
+            format: OutputFormat::Ndjson,
+            ..ParseOptions::default()
+        };
+        let stats = parser.parse_file(&log_path, &output_path, &options).unwrap();
+        assert_eq!(stats.total_lines, 4);
+        
+        let checkpoint = Checkpoint::load(&Checkpoint::path_for(&output_path)).unwrap();
+        assert_eq!(checkpoint.line_num, 3);
+        assert_eq!(checkpoint.byte_offset, (line(1).len() + line(2).len() + line(3).len() + 3) as u64);
+        assert_eq!(checkpoint.schema_fields, vec!["serviceID".to_string()]);
+        
+        // The log grows: the partial line completes and a new field appears
+        let mut file = fs::OpenOptions::new().append(true).open(&log_path).unwrap();
+        writeln!(file, "{}", &line(4)[30..]).unwrap();
+        writeln!(file, "{} volumeID=1207", line(5)).unwrap();
+        
+        options.resume = true;
+        let stats = parser.parse_file(&log_path, &output_path, &options).unwrap();
+        assert_eq!(stats.total_lines, 2);
+        
+        let records: Vec<serde_json::Value> = fs::read_to_string(&output_path).unwrap()
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        let line_nums: Vec<u64> = records.iter().map(|r| r["line_num"].as_u64().unwrap()).collect();
+        assert_eq!(line_nums, vec![1, 2, 3, 4, 5]);
+        assert_eq!(records[3]["serviceID"], 4);
+        assert_eq!(records[4]["volumeID"], 1207);
+        
+        let checkpoint = Checkpoint::load(&Checkpoint::path_for(&output_path)).unwrap();
+        assert_eq!(checkpoint.line_num, 5);
+        assert_eq!(checkpoint.schema_version, 2);
+        
+        // A different file at the same path is refused
+        fs::write(&log_path, format!("{}\n", line(9))).unwrap();
+        assert!(parser.parse_file(&log_path, &output_path, &options).is_err());
+    }
+    
+    #[test]
+    fn test_resume_rejects() {
+        let parser = SolidFireParser::new().unwrap();
+        let temp_dir = TempDir::new().unwrap();
+        let log_path = temp_dir.path().join("sf-master.info");
+        let output_path = temp_dir.path().join("output.ndjson");
+        let rejects_path = temp_dir.path().join("rejects.txt");
+        let line = "2025-06-05T00:20:07.858372Z icpbasi03037 master-1[112875]: [APP-5] [MS] 2069182 BSDirector ms/ClusterStatistics.cpp:1452:GetBlockDriveUsageFromStats| serviceID=230";
+        
+        // The trailing garbage line is still being written, so it is listed
+        // but lies past the checkpoint
+        fs::write(&log_path, format!("{}\ngarbage 1\n{}\ngarb", line, line)).unwrap();
+        let mut options = ParseOptions {
+            format: OutputFormat::Ndjson,
+            rejects_path: Some(rejects_path.clone()),
+            ..ParseOptions::default()
+        };
+        parser.parse_file(&log_path, &output_path, &options).unwrap();
+        assert_eq!(fs::read_to_string(&rejects_path).unwrap(), "2\tgarbage 1\n4\tgarb\n");
+        
+        let mut file = fs::OpenOptions::new().append(true).open(&log_path).unwrap();
+        writeln!(file, "age 2").unwrap();
+        options.resume = true;
+        assert_eq!(parser.parse_file(&log_path, &output_path, &options).unwrap().rejected_lines, 1);
+        assert_eq!(fs::read_to_string(&rejects_path).unwrap(), "2\tgarbage 1\n4\tgarbage 2\n");
+        
+        // The duplicate window isn't checkpointed
+        options.dedup_window = Some(Duration::from_secs(1));
+        let err = parser.parse_file(&log_path, &output_path, &options).unwrap_err();
+        assert!(err.to_string().contains("--dedup"));
+    }
+    
+    #[test]
+    fn test_atomic_output_on_interrupt() {
+        let parser = SolidFireParser::new().unwrap();
+        let temp_dir = TempDir::new().unwrap();
+        let log_path = temp_dir.path().join("sf-master.info");
+        let output_path = temp_dir.path().join("output.json");
+        let line = "2025-06-05T00:20:07.858372Z icpbasi03037 master-1[112875]: [APP-5] [MS] 2069182 BSDirector ms/ClusterStatistics.cpp:1452:GetBlockDriveUsageFromStats| serviceID=230";
+        fs::write(&log_path, format!("{}\n{}\n{}\n{}\n", line, line, line, line)).unwrap();
+        fs::write(&output_path, "previous output").unwrap();
+        
+        // Interrupt requested before the run: stops after the first chunk
+        let mut options = ParseOptions {
+            chunk_size: 2,
+            interrupt: Some(Arc::new(AtomicBool::new(true))),
+            ..ParseOptions::default()
+        };
+        let stats = parser.parse_file(&log_path, &output_path, &options).unwrap();
+        assert!(stats.interrupted);
+        assert_eq!(stats.total_lines, 2);
+        assert_eq!(fs::read_to_string(&output_path).unwrap(), "previous output");
+        assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 2, "temporary file should be removed");
+        
+        options.keep_partial = true;
+        assert!(parser.parse_file(&log_path, &output_path, &options).unwrap().interrupted);
+        let partial: serde_json::Value = serde_json::from_str(&fs::read_to_string(&output_path).unwrap()).unwrap();
+        assert_eq!(partial.as_array().unwrap().len(), 2);
+    }
+    
+    #[test]
+    fn test_run_summary() {
+        let parser = SolidFireParser::new().unwrap();
+        let temp_dir = TempDir::new().unwrap();
+        let log_path = temp_dir.path().join("sf-master.info");
+        let output_path = temp_dir.path().join("output.json");
+        let summary_path = temp_dir.path().join("summary.json");
+        fs::write(&log_path, concat!(
+            "2025-06-05T10:30:15.123456Z icpbasi03037 master-1[112875]: [APP-5] [MS] 2069182 BSDirector ms/ClusterStatistics.cpp:1452:GetBlockDriveUsageFromStats| serviceID=110\n",
+            "2025-06-05T00:20:07.858372Z icpbasi03037 master-1[112875]: [APP-5] [MS] 2069182 BSDirector ms/ClusterStatistics.cpp:1452:GetBlockDriveUsageFromStats| serviceID=230\n",
+            "garbage\n",
+        )).unwrap();
+        
+        let stats = parser.parse_file(&log_path, &output_path, &ParseOptions::default()).unwrap();
+        let (fingerprint, _) = input_fingerprint(&log_path, u64::MAX).unwrap();
+        RunSummary::from_stats(&output_path, RunStatus::ErrorRateExceeded, &stats)
+            .write(&summary_path)
+            .unwrap();
+        
+        let summary: serde_json::Value = serde_json::from_str(&fs::read_to_string(&summary_path).unwrap()).unwrap();
+        assert_eq!(summary["status"], "error_rate_exceeded");
+        assert_eq!(summary["exit_code"], 3);
+        assert_eq!(summary["inputs"][0]["fingerprint"], fingerprint);
+        assert_eq!(summary["lines"], 3);
+        assert_eq!(summary["parse_paths"]["basic"], 2);
+        assert_eq!(summary["parse_paths"]["unparsed"], 1);
+        assert_eq!(summary["dynamic_fields"], 1);
+        // Span is by time, not by line order; the unparsed line has no timestamp
+        assert_eq!(summary["time_span"]["first"], "2025-06-05T00:20:07.858372Z");
+        assert_eq!(summary["time_span"]["last"], "2025-06-05T10:30:15.123456Z");
+    }
+    
+    #[test]
+    fn test_dedup_merged_inputs() {
+        let parser = SolidFireParser::new().unwrap();
+        let temp_dir = TempDir::new().unwrap();
+        let rotated = temp_dir.path().join("sf-master.info.1");
+        let current = temp_dir.path().join("sf-master.info");
+        let output_path = temp_dir.path().join("output.json");
+        let line = |secs: u32, id: u32| format!("2025-06-05T00:20:{:02}.858372Z icpbasi03037 master-1[112875]: [APP-5] [MS] 2069182 BSDirector ms/ClusterStatistics.cpp:1452:GetBlockDriveUsageFromStats| serviceID={}", secs, id);
+        
+        // The files overlap on two lines; the repeated garbage line is kept
+        fs::write(&rotated, format!("{}\n{}\n{}\ngarbage\n", line(1, 1), line(2, 2), line(3, 3))).unwrap();
+        fs::write(&current, format!("{}\n{}\ngarbage\n{}\n", line(2, 2), line(3, 3), line(4, 4))).unwrap();
+        
+        let options = ParseOptions {
+            dedup_window: Some(Duration::from_secs(60)),
+            ..ParseOptions::default()
+        };
+        let stats = parser.parse_files(&[current.clone(), rotated.clone()], &output_path, &options).unwrap();
+        assert_eq!(stats.total_lines, 8);
+        assert_eq!(stats.duplicates_dropped, 2);
+        
+        let records: Vec<serde_json::Value> = serde_json::from_str(&fs::read_to_string(&output_path).unwrap()).unwrap();
+        let ids: Vec<serde_json::Value> = records.iter().map(|r| r["serviceID"].clone()).collect();
+        assert_eq!(ids, vec![1.into(), 2.into(), 3.into(), serde_json::Value::Null, serde_json::Value::Null, 4.into()]);
+        assert_eq!(records[0]["input_file"], rotated.display().to_string());
+        assert_eq!(records[5]["input_file"], current.display().to_string());
+        assert_eq!(records[5]["line_num"], 4);
+    }
+    
+    #[test]
+    fn test_redaction() {
+        let redactor = || Redactor::new(b"team-secret", RedactRule::defaults()).unwrap();
+        let parser = SolidFireParser::new().unwrap().with_redactor(redactor());
+        let api_line = "2025-06-05T09:55:05.594344Z icpbasi03037 master-1[112875]: [APP-5] [API] 3725205 PionThread-402 httpserver/RestAPIServer.cpp:433:operator()|Calling RestAPI::ListVolumeStatsByVolume user=cn=w577934,ou=service accounts,ou=ent,dc=example,dc=com authMethod=Ldap sourceIP=169.28.167.167";
+        let drive_line = "2025-06-12T09:08:32.758370Z icpbasi03037 master-1[112875]: [APP-5] [MS] 2069202 CFaultMon ms/ClusterFaultMonitor.cpp:632:CheckDrives| sip=169.28.167.167 serial=nvme-sn-S438NC0T611278 groupUUID=82d01069-9006-4fc9-b211-d878ac826074 on icpbasi03037";
+        
+        let api = parser.parse_line(api_line, 1);
+        let drive = parser.parse_line(drive_line, 2);
+        for secret in ["icpbasi03037", "w577934", "service accounts", "169.28.167.167", "S438NC0T611278", "82d01069"] {
+            assert!(!api.raw_line.contains(secret) && !drive.raw_line.contains(secret), "{} leaked", secret);
+        }
+        
+        // One value, one token: across fields, lines and parser instances
+        let ip = redactor().pseudonym("ip", "169.28.167.167");
+        assert_eq!(api.dynamic_fields["sourceIP"], ip.as_str());
+        assert_eq!(drive.dynamic_fields["sip"], ip.as_str());
+        assert_eq!(api.hostname.as_deref(), Some(redactor().pseudonym("host", "icpbasi03037").as_str()));
+        assert_eq!(drive.hostname, api.hostname);
+        assert!(drive.content.as_deref().unwrap().ends_with(&format!("on {}", drive.hostname.as_deref().unwrap())));
+        // The whole DN is one value, so its parts don't become stray fields
+        assert_eq!(api.dynamic_fields["user"], redactor().pseudonym("user", "cn=w577934,ou=service accounts,ou=ent,dc=example,dc=com").as_str());
+        assert_eq!(api.dynamic_fields["authMethod"], "Ldap");
+        assert!(!api.dynamic_fields.keys().any(|key| key.contains(",")));
+    }
+    
+    #[test]
+    fn test_template_mining() {
+        let parser = SolidFireParser::new().unwrap();
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.log");
+        let output_path = temp_dir.path().join("output.json");
+        let catalog_path = temp_dir.path().join("templates.json");
+        let line = |secs: u32, id: u32| format!("2025-06-12T08:35:{:02}.178356Z icpbasi03037 master-1[112875]: [APP-5] [Snaps] 2069183 Scheduler cs/CServiceSliceSnapshots.cpp:866:UnregisterSnapshotInternal|Deleting empty group snapshot groupSnapshotID={}", secs, id);
+        fs::write(&input_path, format!("{}\n{}\ngarbage\n", line(0, 13933489), line(1, 13933490))).unwrap();
+        
+        let options = ParseOptions {
+            templates_path: Some(catalog_path.clone()),
+            ..ParseOptions::default()
+        };
+        let stats = parser.parse_file(&input_path, &output_path, &options).unwrap();
+        assert_eq!(stats.template_count, 2);
+        
+        let records: Vec<serde_json::Value> = serde_json::from_str(&fs::read_to_string(&output_path).unwrap()).unwrap();
+        assert_eq!(records[0]["template_id"], records[1]["template_id"]);
+        assert_eq!(records[1]["template"], "Deleting empty group snapshot groupSnapshotID=<*>");
+        assert_ne!(records[2]["template_id"], records[0]["template_id"]);
+        
+        let catalog: serde_json::Value = serde_json::from_str(&fs::read_to_string(&catalog_path).unwrap()).unwrap();
+        assert_eq!(catalog[0]["template_id"], records[0]["template_id"]);
+        assert_eq!(catalog[0]["count"], 2);
+        assert_eq!(catalog[0]["last_seen"], "2025-06-12T08:35:01.178356Z");
+    }
+    
+    #[test]
+    fn test_split_by() {
+        let parser = SolidFireParser::new().unwrap();
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.log");
+        let output_dir = temp_dir.path().join("split");
+        fs::write(&input_path, "2025-06-05T00:20:07.858372Z icpbasi03037 master-1[112875]: [APP-5] [MS] 2069182 BSDirector ms/ClusterStatistics.cpp:1452:GetBlockDriveUsageFromStats| serviceID=230
+2025-06-12T08:35:00.177183Z icpbasi03037 master-1[112875]: [APP-5] [Vvols] 2069183 Scheduler cs/CServiceSliceSnapshots.cpp:1037:UnregisterSnapshot| snapshotID=13846639
+2025-06-12T08:36:00.000000Z icpbasi03037 master-1[112875]: [APP-5] [MS] 2069182 BSDirector ms/ClusterStatistics.cpp:1452:GetBlockDriveUsageFromStats| serviceID=110
+garbage
+").unwrap();
+        
+        let options = ParseOptions {
+            split_by: Some(SplitBy::Component),
+            ..ParseOptions::default()
+        };
+        let stats = parser.parse_file(&input_path, &output_dir, &options).unwrap();
+        assert_eq!(stats.total_lines, 4);
+        
+        let ms: Vec<serde_json::Value> = serde_json::from_str(&fs::read_to_string(output_dir.join("MS.json")).unwrap()).unwrap();
+        assert_eq!(ms.len(), 2);
+        assert_eq!(ms[1]["serviceID"], 110);
+        // Every partition shares the run's schema
+        assert!(ms[0].as_object().unwrap().contains_key("snapshotID"));
+        assert!(output_dir.join("Vvols.json").exists());
+        assert!(output_dir.join("unknown.json").exists());
+        
+        let manifest: serde_json::Value = serde_json::from_str(&fs::read_to_string(output_dir.join(MANIFEST_FILE)).unwrap()).unwrap();
+        assert_eq!(manifest["split_by"], "component");
+        assert_eq!(manifest["records"], 4);
+        let partitions = manifest["partitions"].as_array().unwrap();
+        assert_eq!(partitions.len(), 3);
+        assert_eq!(partitions[0]["partition"], "MS");
+        assert_eq!(partitions[0]["records"], 2);
+        assert_eq!(partitions[0]["time_span"]["first"], "2025-06-05T00:20:07.858372Z");
+        assert_eq!(partitions[0]["time_span"]["last"], "2025-06-12T08:36:00.000000Z");
+        assert!(parser.parse_file(&input_path, Path::new("-"), &options).is_err());
+    }
+    
+    #[test]
+    fn test_parquet_dataset() {
+        use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+        use parquet::file::statistics::Statistics;
+        
+        let parser = SolidFireParser::new().unwrap();
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.log");
+        let output_dir = temp_dir.path().join("dataset");
+        fs::write(&input_path, "2025-06-05T00:20:07.858372Z icpbasi03037 master-1[112875]: [APP-5] [MS] 2069182 BSDirector ms/ClusterStatistics.cpp:1452:GetBlockDriveUsageFromStats| serviceID=230 mode=1
+2025-06-05T00:21:07.000000Z icpbasi03037 master-1[112875]: [APP-5] [MS] 2069182 BSDirector ms/ClusterStatistics.cpp:1452:GetBlockDriveUsageFromStats| serviceID=110 mode=fast
+2025-06-12T08:35:00.177183Z icpbasi03037 master-1[112875]: [APP-5] [Snaps/Vvols] 2069183 Scheduler cs/CServiceSliceSnapshots.cpp:1037:UnregisterSnapshot| snapshotID=13846639
+garbage
+").unwrap();
+        
+        let options = ParseOptions {
+            format: OutputFormat::Parquet,
+            ..ParseOptions::default()
+        };
+        parser.parse_file(&input_path, &output_dir, &options).unwrap();
+        
+        let ms = output_dir.join("date=2025-06-05/component=MS/part-0.parquet");
+        assert!(output_dir.join("date=2025-06-12/component=Snaps%2FVvols/part-0.parquet").exists());
+        assert!(output_dir.join("date=__HIVE_DEFAULT_PARTITION__/component=__HIVE_DEFAULT_PARTITION__/part-0.parquet").exists());
+        // The spool is gone once the dataset is written
+        assert_eq!(fs::read_dir(&output_dir).unwrap().count(), 3);
+        
+        let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(&ms).unwrap()).unwrap();
+        let metadata = reader.metadata().clone();
+        let schema = reader.schema().clone();
+        // Partition columns live in the path only; types hold for the whole dataset
+        assert!(schema.field_with_name("date").is_err() && schema.field_with_name("component").is_err());
+        assert!(matches!(schema.field_with_name("timestamp").unwrap().data_type(), arrow::datatypes::DataType::Timestamp(_, _)));
+        assert_eq!(schema.field_with_name("serviceID").unwrap().data_type(), &arrow::datatypes::DataType::Int64);
+        assert_eq!(schema.field_with_name("mode").unwrap().data_type(), &arrow::datatypes::DataType::Utf8);
+        assert!(schema.field_with_name("snapshotID").is_ok());
+        
+        let batches: Vec<_> = reader.build().unwrap().collect::<Result<_, _>>().unwrap();
+        assert_eq!(batches.iter().map(|b| b.num_rows()).sum::<usize>(), 2);
+        
+        let row_group = metadata.row_group(0);
+        let column = |name: &str| row_group.columns().iter().find(|c| c.column_path().string() == name).unwrap();
+        match column("serviceID").statistics() {
+            Some(Statistics::Int64(stats)) => assert_eq!((stats.min_opt(), stats.max_opt()), (Some(&110), Some(&230))),
+            other => panic!("unexpected serviceID statistics: {:?}", other),
+        }
+        assert!(column("timestamp").statistics().is_some());
+        assert!(column("raw_line").statistics().is_none());
+        
+        // A second run replaces the dataset only when asked to, and leaves no staging behind
+        let error = parser.parse_file(&input_path, &output_dir, &options).unwrap_err().to_string();
+        assert!(error.contains("--overwrite"), "{}", error);
+        let options = ParseOptions { overwrite: true, ..options };
+        parser.parse_file(&input_path, &output_dir, &options).unwrap();
+        assert!(ms.exists());
+        assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 2);
+    }
+    
     #[test]
     fn test_nushell_query_compatibility() {
         let parser = SolidFireParser::new().unwrap();
@@ -537,7 +1819,9 @@ mod tests {
         
         // Parse with Rust parser
         let output_path = temp_dir.path().join("output.json");
-        parser.parse_file(&test_log_path, &output_path, 1000).unwrap();
+        let stats = parser.parse_file(&test_log_path, &output_path, &ParseOptions::default()).unwrap();
+        assert_eq!(stats.total_lines, 4);
+        assert_eq!(stats.format_counts.get(LogFormat::Basic), 4);
         
         // Verify file was created and is valid JSON
         assert!(output_path.exists(), "Output JSON file should be created");
@@ -0,0 +1,173 @@
+use chrono::{DateTime, Utc};
+use std::io::{self, BufRead};
+
+use crate::reader::{DecodedLine, LossyLines};
+
+/// A decoded line tagged with where it came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InputLine {
+    /// Index into the list of inputs
+    pub input: usize,
+    pub line_num: u32,
+    pub line: DecodedLine,
+}
+
+struct MergeInput<R> {
+    lines: LossyLines<R>,
+    line_num: u32,
+    head: Option<InputLine>,
+    /// Timestamp of the head line, or of the last line that had one
+    key: Option<DateTime<Utc>>,
+}
+
+/// Reads several logs as one stream ordered by timestamp, like a k-way
+/// merge of already-sorted files. Rotated logs and overlapping extracts of
+/// the same log come out interleaved, so identical lines end up close
+/// together in time.
+///
+/// Lines without a leading timestamp (continuations, garbage) sort with the
+/// line before them in the same input. On ties the input that produced the
+/// previous line goes first, so runs from one file aren't split up.
+///
+/// A single input is passed through without reading ahead, which keeps
+/// `complete_offset` and `complete_lines` exact for checkpoints.
+pub struct MergedLines<R> {
+    inputs: Vec<MergeInput<R>>,
+    last_input: usize,
+    primed: bool,
+}
+
+impl<R: BufRead> MergedLines<R> {
+    /// Merge `inputs`, each paired with the number of lines already
+    /// consumed from it (non-zero when resuming).
+    pub fn new(inputs: Vec<(LossyLines<R>, u32)>) -> Self {
+        MergedLines {
+            inputs: inputs
+                .into_iter()
+                .map(|(lines, line_num)| MergeInput {
+                    lines,
+                    line_num,
+                    head: None,
+                    key: None,
+                })
+                .collect(),
+            last_input: 0,
+            primed: false,
+        }
+    }
+
+    /// Offset just past the last complete line of the first input. Only
+    /// meaningful with a single input.
+    pub fn complete_offset(&self) -> u64 {
+        self.inputs[0].lines.complete_offset()
+    }
+
+    /// Complete lines read from the first input. Only meaningful with a
+    /// single input.
+    pub fn complete_lines(&self) -> u64 {
+        self.inputs[0].lines.complete_lines()
+    }
+
+    fn advance(&mut self, index: usize) -> io::Result<()> {
+        let input = &mut self.inputs[index];
+        input.head = match input.lines.next().transpose()? {
+            Some(line) => {
+                input.line_num += 1;
+                if let Some(timestamp) = timestamp_key(&line.text) {
+                    input.key = Some(timestamp);
+                }
+                Some(InputLine {
+                    input: index,
+                    line_num: input.line_num,
+                    line,
+                })
+            }
+            None => None,
+        };
+        Ok(())
+    }
+
+    fn next_merged(&mut self) -> io::Result<Option<InputLine>> {
+        if !self.primed {
+            for index in 0..self.inputs.len() {
+                self.advance(index)?;
+            }
+            self.primed = true;
+        }
+
+        let mut best: Option<usize> = None;
+        for (index, input) in self.inputs.iter().enumerate() {
+            if input.head.is_none() {
+                continue;
+            }
+            best = match best {
+                Some(current) => {
+                    let current_key = self.inputs[current].key;
+                    if input.key < current_key || (input.key == current_key && index == self.last_input) {
+                        Some(index)
+                    } else {
+                        Some(current)
+                    }
+                }
+                None => Some(index),
+            };
+        }
+
+        let Some(index) = best else {
+            return Ok(None);
+        };
+        let line = self.inputs[index].head.take();
+        self.last_input = index;
+        self.advance(index)?;
+        Ok(line)
+    }
+}
+
+impl<R: BufRead> Iterator for MergedLines<R> {
+    type Item = io::Result<InputLine>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.inputs.len() == 1 {
+            let input = &mut self.inputs[0];
+            return input.lines.next().map(|line| {
+                line.map(|line| {
+                    input.line_num += 1;
+                    InputLine {
+                        input: 0,
+                        line_num: input.line_num,
+                        line,
+                    }
+                })
+            });
+        }
+        self.next_merged().transpose()
+    }
+}
+
+/// The leading ISO-8601 timestamp of a log line, if it has one. Parsed
+/// rather than compared as text, since the fraction's width varies
+/// (`07.5Z` vs `07.858372Z`).
+fn timestamp_key(text: &str) -> Option<DateTime<Utc>> {
+    let token = text.split_whitespace().next()?;
+    DateTime::parse_from_rfc3339(token).ok().map(|timestamp| timestamp.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_by_parsed_timestamp() {
+        let a: &[u8] = b"2025-06-05T00:20:07.50001Z a1\n  continued\n2025-06-05T00:20:09Z a2\n";
+        let b: &[u8] = b"2025-06-05T00:20:07.5Z b1\n2025-06-05T02:20:08+02:00 b2\n";
+        let merged = MergedLines::new(vec![(LossyLines::new(a, 1024), 0), (LossyLines::new(b, 1024), 0)]);
+        let texts: Vec<String> = merged.map(|line| line.unwrap().line.text).collect();
+        assert_eq!(texts, [
+            "2025-06-05T00:20:07.5Z b1",
+            "2025-06-05T00:20:07.50001Z a1",
+            "  continued",
+            "2025-06-05T02:20:08+02:00 b2",
+            "2025-06-05T00:20:09Z a2",
+        ]);
+    }
+}
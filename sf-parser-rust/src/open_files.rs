@@ -0,0 +1,72 @@
+use std::borrow::Borrow;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+
+/// Most files a partitioned writer keeps open at once; well under the usual
+/// 1024 descriptor limit, leaving room for inputs and a second writer.
+pub const MAX_OPEN_FILES: usize = 256;
+
+/// Tracks which of a writer's per-partition files are open, least recently
+/// used first, so a run with thousands of partitions (hours, components)
+/// holds at most `limit` handles. The writer closes the file `touch` hands
+/// back and reopens it in append mode the next time its partition is used.
+#[derive(Debug)]
+pub struct OpenFiles<K> {
+    limit: usize,
+    tick: u64,
+    by_use: BTreeMap<u64, K>,
+    last_use: HashMap<K, u64>,
+}
+
+impl<K: Clone + Eq + Hash> OpenFiles<K> {
+    pub fn new(limit: usize) -> Self {
+        OpenFiles {
+            limit: limit.max(1),
+            tick: 0,
+            by_use: BTreeMap::new(),
+            last_use: HashMap::new(),
+        }
+    }
+
+    /// Mark `key`'s file as used and open. Returns the least recently used
+    /// file to close if that takes the count over the limit; never `key`.
+    pub fn touch<Q>(&mut self, key: &Q) -> Option<K>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ToOwned<Owned = K> + ?Sized,
+    {
+        self.tick += 1;
+        if let Some(last) = self.last_use.get_mut(key) {
+            let key = self.by_use.remove(last).expect("open file has a use");
+            *last = self.tick;
+            self.by_use.insert(self.tick, key);
+            return None;
+        }
+
+        let key = key.to_owned();
+        self.last_use.insert(key.clone(), self.tick);
+        self.by_use.insert(self.tick, key);
+        if self.last_use.len() <= self.limit {
+            return None;
+        }
+        let (_, evicted) = self.by_use.pop_first().expect("over the limit, so not empty");
+        self.last_use.remove::<K>(&evicted);
+        Some(evicted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_touch_evicts_least_recently_used() {
+        let mut open = OpenFiles::new(2);
+        assert_eq!(open.touch("a"), None);
+        assert_eq!(open.touch("b"), None);
+        assert_eq!(open.touch("a"), None);
+        assert_eq!(open.touch("c"), Some("b".to_string()));
+        assert_eq!(open.touch("b"), Some("a".to_string()));
+        assert_eq!(open.touch("b"), None);
+    }
+}
@@ -0,0 +1,429 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+/// Output document layout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// A single JSON array, the layout `open file.json` expects in Nushell
+    Json,
+    /// One JSON object per line; can be appended to and streamed
+    Ndjson,
+    /// A Hive-partitioned directory of Parquet files (see `ParquetDataset`)
+    Parquet,
+}
+
+impl OutputFormat {
+    pub fn from_name(name: &str) -> Option<OutputFormat> {
+        match name.to_ascii_lowercase().as_str() {
+            "json" => Some(OutputFormat::Json),
+            "ndjson" | "jsonl" => Some(OutputFormat::Ndjson),
+            "parquet" => Some(OutputFormat::Parquet),
+            _ => None,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Json => "json",
+            OutputFormat::Ndjson => "ndjson",
+            OutputFormat::Parquet => "parquet",
+        }
+    }
+}
+
+/// `-` stands for stdin as an input path and stdout as an output path.
+pub fn is_stdio(path: &Path) -> bool {
+    path == Path::new("-")
+}
+
+/// True if the error comes from writing to a pipe whose reader has gone
+/// away (e.g. `| head`), which a pipeline stage should treat as a clean exit.
+pub fn is_broken_pipe(err: &anyhow::Error) -> bool {
+    err.chain().any(|cause| {
+        cause
+            .downcast_ref::<io::Error>()
+            .is_some_and(|e| e.kind() == io::ErrorKind::BrokenPipe)
+    })
+}
+
+/// A file written under a temporary name next to its destination and
+/// renamed into place by `commit`. If it is dropped without being committed
+/// (an error or an interrupt unwound past it), the temporary file is removed
+/// and whatever was at the destination before is left untouched.
+pub struct AtomicFile {
+    /// `None` while suspended
+    file: Option<File>,
+    tmp_path: PathBuf,
+    final_path: PathBuf,
+    committed: bool,
+}
+
+impl AtomicFile {
+    pub fn create(path: &Path) -> Result<Self> {
+        let file_name = path
+            .file_name()
+            .with_context(|| format!("Output path has no file name: {}", path.display()))?;
+        let mut tmp_name = std::ffi::OsString::from(".");
+        tmp_name.push(file_name);
+        tmp_name.push(format!(".tmp-{}", std::process::id()));
+        let tmp_path = path.with_file_name(tmp_name);
+
+        let file = File::create(&tmp_path)
+            .with_context(|| format!("Failed to create output file: {}", tmp_path.display()))?;
+        Ok(AtomicFile {
+            file: Some(file),
+            tmp_path,
+            final_path: path.to_path_buf(),
+            committed: false,
+        })
+    }
+
+    /// Close the file handle, keeping the temporary file for `resume`.
+    pub fn suspend(&mut self) {
+        self.file = None;
+    }
+
+    /// Reopen a suspended file to append to it.
+    pub fn resume(&mut self) -> Result<()> {
+        if self.file.is_none() {
+            self.file = Some(open_append(&self.tmp_path)?);
+        }
+        Ok(())
+    }
+
+    /// Sync the data to disk and atomically replace the destination.
+    pub fn commit(mut self) -> Result<()> {
+        self.resume()?;
+        if let Some(file) = &self.file {
+            file.sync_all()?;
+        }
+        fs::rename(&self.tmp_path, &self.final_path).with_context(|| {
+            format!("Failed to move output into place: {}", self.final_path.display())
+        })?;
+        self.committed = true;
+        Ok(())
+    }
+}
+
+impl Write for AtomicFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file.as_mut().ok_or_else(suspended)?.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.as_mut().map_or(Ok(()), |file| file.flush())
+    }
+}
+
+fn suspended() -> io::Error {
+    io::Error::other("write to a suspended output file")
+}
+
+fn open_append(path: &Path) -> Result<File> {
+    OpenOptions::new()
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to reopen output file: {}", path.display()))
+}
+
+impl Drop for AtomicFile {
+    fn drop(&mut self) {
+        if !self.committed {
+            let _ = fs::remove_file(&self.tmp_path);
+        }
+    }
+}
+
+/// A directory built under a temporary name next to its destination and
+/// swapped into place by `commit`, so a new run never mixes with an old
+/// one's files. Dropping it without committing removes the staged tree and
+/// leaves the destination as it was.
+pub struct StagedDir {
+    dir: PathBuf,
+    staging: TempDir,
+}
+
+impl StagedDir {
+    /// Stage a replacement for `dir`. Fails if `dir` is not empty, unless
+    /// `overwrite` is set and every entry in it passes `replaceable`, i.e.
+    /// looks like the output of an earlier run (`what` names that output in
+    /// errors).
+    pub fn create(dir: &Path, overwrite: bool, what: &str, replaceable: impl Fn(&str) -> bool) -> Result<Self> {
+        let name = dir
+            .file_name()
+            .with_context(|| format!("Output path has no directory name: {}", dir.display()))?;
+        if let Ok(entries) = fs::read_dir(dir) {
+            let entries = entries.map(|entry| entry.map(|e| e.file_name())).collect::<io::Result<Vec<_>>>()?;
+            if !entries.is_empty() && !overwrite {
+                anyhow::bail!("Output directory {} is not empty; pass --overwrite to replace the {} in it", dir.display(), what);
+            }
+            if let Some(other) = entries.iter().find(|entry| !replaceable(&entry.to_string_lossy())) {
+                anyhow::bail!("Refusing to replace {}: {} is not part of a {}",
+                    dir.display(), other.to_string_lossy(), what);
+            }
+        }
+
+        let parent = dir.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create output directory: {}", parent.display()))?;
+        let staging = tempfile::Builder::new()
+            .prefix(&format!(".{}.tmp-", name.to_string_lossy()))
+            .tempdir_in(parent)
+            .with_context(|| format!("Failed to create staging directory in {}", parent.display()))?;
+        Ok(StagedDir {
+            dir: dir.to_path_buf(),
+            staging,
+        })
+    }
+
+    /// Where to build the new tree.
+    pub fn path(&self) -> &Path {
+        self.staging.path()
+    }
+
+    /// Swap the staged tree in; the old one is only removed once the new
+    /// one is in place.
+    pub fn commit(self) -> Result<()> {
+        let staged = self.staging.path();
+        if self.dir.exists() {
+            let mut old_name = staged.file_name().expect("staging has a name").to_os_string();
+            old_name.push(".old");
+            let old = staged.with_file_name(old_name);
+            fs::rename(&self.dir, &old)
+                .with_context(|| format!("Failed to move the old output aside: {}", self.dir.display()))?;
+            if let Err(e) = fs::rename(staged, &self.dir) {
+                let _ = fs::rename(&old, &self.dir);
+                return Err(e).with_context(|| format!("Failed to move the output into place: {}", self.dir.display()));
+            }
+            fs::remove_dir_all(&old)
+                .with_context(|| format!("Failed to remove the old output: {}", old.display()))?;
+        } else {
+            fs::rename(staged, &self.dir)
+                .with_context(|| format!("Failed to move the output into place: {}", self.dir.display()))?;
+        }
+        let _ = self.staging.keep();
+        Ok(())
+    }
+}
+
+/// Where a `RecordWriter` sends its bytes. Output goes through an
+/// `AtomicFile` so a failed run leaves the previous file alone; checkpointed
+/// NDJSON is written in place instead, because the checkpoint records how
+/// much of the file is complete and `--resume` appends to it.
+enum OutputTarget {
+    Atomic(AtomicFile),
+    /// `None` while suspended
+    Direct(Option<File>),
+    Stdout(io::Stdout),
+}
+
+impl Write for OutputTarget {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            OutputTarget::Atomic(file) => file.write(buf),
+            OutputTarget::Direct(file) => file.as_mut().ok_or_else(suspended)?.write(buf),
+            OutputTarget::Stdout(stdout) => stdout.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            OutputTarget::Atomic(file) => file.flush(),
+            OutputTarget::Direct(file) => file.as_mut().map_or(Ok(()), |file| file.flush()),
+            OutputTarget::Stdout(stdout) => stdout.flush(),
+        }
+    }
+}
+
+/// Writes records in the chosen format and keeps track of how many bytes
+/// of the output are complete, so checkpoints can point into the file.
+pub struct RecordWriter {
+    out: BufWriter<OutputTarget>,
+    path: PathBuf,
+    format: OutputFormat,
+    records: usize,
+    bytes_written: u64,
+}
+
+impl RecordWriter {
+    /// Write to `path`, or to stdout when `path` is `-`.
+    pub fn create(path: &Path, format: OutputFormat) -> Result<Self> {
+        let target = match format {
+            _ if is_stdio(path) => OutputTarget::Stdout(io::stdout()),
+            OutputFormat::Json | OutputFormat::Ndjson => OutputTarget::Atomic(AtomicFile::create(path)?),
+            OutputFormat::Parquet => anyhow::bail!("Parquet is written as a dataset directory, not a record stream"),
+        };
+        RecordWriter::start(target, path, format)
+    }
+
+    /// Write NDJSON straight to `path`, so each checkpoint can point at the
+    /// complete part of the file and `append` can pick up from there.
+    pub fn create_in_place(path: &Path) -> Result<Self> {
+        let file = File::create(path)
+            .with_context(|| format!("Failed to create output file: {}", path.display()))?;
+        RecordWriter::start(OutputTarget::Direct(Some(file)), path, OutputFormat::Ndjson)
+    }
+
+    fn start(target: OutputTarget, path: &Path, format: OutputFormat) -> Result<Self> {
+        let mut writer = RecordWriter {
+            out: BufWriter::new(target),
+            path: path.to_path_buf(),
+            format,
+            records: 0,
+            bytes_written: 0,
+        };
+        if format == OutputFormat::Json {
+            // Write JSON array start
+            writer.write_raw("[\n")?;
+        }
+        Ok(writer)
+    }
+
+    /// Reopen an NDJSON output for appending, discarding anything past
+    /// `offset` (records written after the last checkpoint).
+    pub fn append(path: &Path, offset: u64) -> Result<Self> {
+        let mut file = OpenOptions::new()
+            .write(true)
+            .open(path)
+            .with_context(|| format!("Failed to open output file for append: {}", path.display()))?;
+        let len = file.metadata()?.len();
+        if len < offset {
+            anyhow::bail!(
+                "Output file {} is shorter ({} bytes) than the checkpoint expects ({} bytes)",
+                path.display(), len, offset
+            );
+        }
+        file.set_len(offset)?;
+        file.seek(SeekFrom::End(0))?;
+        Ok(RecordWriter {
+            out: BufWriter::new(OutputTarget::Direct(Some(file))),
+            path: path.to_path_buf(),
+            format: OutputFormat::Ndjson,
+            records: 0,
+            bytes_written: offset,
+        })
+    }
+
+    pub fn write_record<T: Serialize>(&mut self, record: &T) -> Result<()> {
+        let json = serde_json::to_string(record)?;
+        match self.format {
+            OutputFormat::Json => {
+                if self.records > 0 {
+                    self.write_raw(",\n")?;
+                }
+                self.write_raw("  ")?;
+                self.write_raw(&json)?;
+            }
+            OutputFormat::Ndjson => {
+                self.write_raw(&json)?;
+                self.write_raw("\n")?;
+            }
+            OutputFormat::Parquet => unreachable!("RecordWriter is never created for Parquet"),
+        }
+        self.records += 1;
+        Ok(())
+    }
+
+    pub fn records(&self) -> usize {
+        self.records
+    }
+
+    /// Total bytes in the output file once buffered data is flushed.
+    pub fn bytes_written(&self) -> u64 {
+        self.bytes_written
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        self.out.flush()?;
+        Ok(())
+    }
+
+    /// Flush and close the output's file handle without closing the
+    /// document; `resume` reopens it in append mode. Lets a writer with a
+    /// file per partition stay under the open-file limit.
+    pub fn suspend(&mut self) -> Result<()> {
+        self.out.flush()?;
+        match self.out.get_mut() {
+            OutputTarget::Atomic(file) => file.suspend(),
+            OutputTarget::Direct(file) => *file = None,
+            OutputTarget::Stdout(_) => {}
+        }
+        Ok(())
+    }
+
+    /// Reopen a suspended output; does nothing if it is open.
+    pub fn resume(&mut self) -> Result<()> {
+        match self.out.get_mut() {
+            OutputTarget::Atomic(file) => file.resume()?,
+            OutputTarget::Direct(file @ None) => *file = Some(open_append(&self.path)?),
+            OutputTarget::Direct(Some(_)) | OutputTarget::Stdout(_) => {}
+        }
+        Ok(())
+    }
+
+    /// Close the document, flush it and move it into place. Also used with
+    /// `--keep-partial` to turn an interrupted run into a valid document.
+    pub fn finish(mut self) -> Result<()> {
+        self.resume()?;
+        if self.format == OutputFormat::Json {
+            // Write JSON array end
+            self.write_raw("\n]\n")?;
+        }
+        let target = self.out.into_inner().map_err(|e| e.into_error())?;
+        match target {
+            OutputTarget::Atomic(file) => file.commit(),
+            OutputTarget::Direct(file) => {
+                if let Some(mut file) = file {
+                    file.flush()?;
+                }
+                Ok(())
+            }
+            OutputTarget::Stdout(mut stdout) => {
+                stdout.flush()?;
+                Ok(())
+            }
+        }
+    }
+
+    fn write_raw(&mut self, s: &str) -> Result<()> {
+        self.out.write_all(s.as_bytes())?;
+        self.bytes_written += s.len() as u64;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_ndjson_replaced_on_finish() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("output.ndjson");
+        fs::write(&path, "previous output\n").unwrap();
+
+        // An unfinished writer leaves the previous file alone
+        let mut writer = RecordWriter::create(&path, OutputFormat::Ndjson).unwrap();
+        writer.write_record(&serde_json::json!({"line_num": 1})).unwrap();
+        writer.flush().unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "previous output\n");
+        drop(writer);
+        assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 1);
+
+        let mut writer = RecordWriter::create(&path, OutputFormat::Ndjson).unwrap();
+        writer.write_record(&serde_json::json!({"line_num": 1})).unwrap();
+        writer.finish().unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "{\"line_num\":1}\n");
+
+        // The checkpointed writer works on the file itself
+        let mut writer = RecordWriter::create_in_place(&path).unwrap();
+        writer.write_record(&serde_json::json!({"line_num": 2})).unwrap();
+        writer.flush().unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "{\"line_num\":2}\n");
+    }
+}
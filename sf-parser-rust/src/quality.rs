@@ -0,0 +1,191 @@
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+
+use crate::format::FormatCounts;
+use crate::LogRecord;
+
+/// Number of distinct failure shapes shown in the quality report.
+pub const TOP_FAILURE_SHAPES: usize = 20;
+
+/// Most distinct failure shapes tracked; failures with shapes first seen
+/// after that are only counted, so garbage input can't grow the map without
+/// bound.
+pub const MAX_FAILURE_SHAPES: usize = 1000;
+
+/// How many leading tokens of a line contribute to its shape.
+const SHAPE_TOKENS: usize = 8;
+
+/// A group of failed lines that share the same structure.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FailureShape {
+    pub shape: String,
+    pub count: usize,
+    pub sample_line_num: u32,
+    pub sample: String,
+}
+
+/// Groups lines that failed to parse by their structural shape so the report
+/// shows "what kinds of lines break" rather than every individual line.
+#[derive(Debug, Clone, Default)]
+pub struct FailureShapes {
+    shapes: HashMap<String, FailureShape>,
+    /// Failures whose shape arrived after `MAX_FAILURE_SHAPES` were tracked
+    untracked: usize,
+}
+
+impl FailureShapes {
+    /// Record a parsed line; lines without a `parse_error` are ignored.
+    pub fn record(&mut self, record: &LogRecord) {
+        let Some(code) = record.parse_error else {
+            return;
+        };
+        let shape = format!("{}: {}", code, line_shape(&record.raw_line));
+        if self.shapes.len() >= MAX_FAILURE_SHAPES && !self.shapes.contains_key(&shape) {
+            self.untracked += 1;
+            return;
+        }
+        self.shapes
+            .entry(shape.clone())
+            .or_insert_with(|| FailureShape {
+                shape,
+                count: 0,
+                sample_line_num: record.line_num,
+                sample: record.raw_line.clone(),
+            })
+            .count += 1;
+    }
+
+    pub fn len(&self) -> usize {
+        self.shapes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.shapes.is_empty()
+    }
+
+    /// Failed lines not counted under any shape because the limit was hit.
+    pub fn untracked(&self) -> usize {
+        self.untracked
+    }
+
+    /// The `n` most frequent shapes, ties broken by first occurrence.
+    pub fn top(&self, n: usize) -> Vec<&FailureShape> {
+        let mut shapes: Vec<&FailureShape> = self.shapes.values().collect();
+        shapes.sort_by(|a, b| {
+            b.count
+                .cmp(&a.count)
+                .then(a.sample_line_num.cmp(&b.sample_line_num))
+        });
+        shapes.truncate(n);
+        shapes
+    }
+}
+
+/// Reduce a line to its structure: runs of digits become `#`, runs of
+/// letters become `a`, and everything else is kept. Only the first few
+/// tokens are considered so free-text tails don't split otherwise identical
+/// failures.
+pub fn line_shape(line: &str) -> String {
+    let mut shape = String::new();
+    for (i, token) in line.split_whitespace().take(SHAPE_TOKENS).enumerate() {
+        if i > 0 {
+            shape.push(' ');
+        }
+        let mut last = None;
+        for c in token.chars() {
+            let class = if c.is_ascii_digit() {
+                '#'
+            } else if c.is_alphabetic() {
+                'a'
+            } else {
+                c
+            };
+            if Some(class) != last || !(class == '#' || class == 'a') {
+                shape.push(class);
+            }
+            last = Some(class);
+        }
+    }
+    if shape.is_empty() {
+        shape.push_str("<empty>");
+    }
+    shape
+}
+
+/// Parse an error rate given as a fraction (`0.01`) or a percentage (`1%`).
+pub fn parse_rate(value: &str) -> Result<f64> {
+    let rate = match value.trim().strip_suffix('%') {
+        Some(percent) => percent.trim().parse::<f64>().map(|p| p / 100.0),
+        None => value.trim().parse::<f64>(),
+    }
+    .with_context(|| format!("Invalid error rate: {}", value))?;
+
+    if !(0.0..=1.0).contains(&rate) {
+        anyhow::bail!("Error rate must be between 0 and 1 (or 0% and 100%): {}", value);
+    }
+    Ok(rate)
+}
+
+/// Render the end-of-run parse-quality report.
+pub fn quality_report(format_counts: &FormatCounts, failures: &FailureShapes) -> String {
+    let mut report = format_counts.summary_table();
+    if failures.is_empty() {
+        return report;
+    }
+
+    if failures.untracked() > 0 {
+        report.push_str(&format!(
+            "Top failure shapes ({} tracked, {} more lines in shapes past the limit):\n",
+            failures.len(),
+            failures.untracked()
+        ));
+    } else {
+        report.push_str(&format!(
+            "Top failure shapes ({} distinct):\n",
+            failures.len()
+        ));
+    }
+    for shape in failures.top(TOP_FAILURE_SHAPES) {
+        let mut sample: String = shape.sample.chars().take(120).collect();
+        if sample.len() < shape.sample.len() {
+            sample.push('…');
+        }
+        report.push_str(&format!(
+            "  {:>8}  {}\n            line {}: {}\n",
+            shape.count, shape.shape, shape.sample_line_num, sample
+        ));
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_shape() {
+        assert_eq!(line_shape("Jun 5 00:20:07 icpbasi03037 kernel: link down"), "a # #:#:# a# a: a a");
+        assert_eq!(line_shape("   "), "<empty>");
+    }
+
+    #[test]
+    fn test_shape_limit() {
+        let parser = crate::SolidFireParser::new().unwrap();
+        let mut shapes = FailureShapes::default();
+        for i in 0..MAX_FAILURE_SHAPES + 5 {
+            // Punctuation is kept as is, so each line has its own shape
+            let line = format!("{:b}", i).replace('0', ".").replace('1', ":");
+            shapes.record(&parser.parse_line(&line, i as u32 + 1));
+        }
+        assert_eq!(shapes.len(), MAX_FAILURE_SHAPES);
+        assert_eq!(shapes.untracked(), 5);
+    }
+
+    #[test]
+    fn test_parse_rate() {
+        assert_eq!(parse_rate("1%").unwrap(), 0.01);
+        assert_eq!(parse_rate("0.25").unwrap(), 0.25);
+        assert!(parse_rate("150%").is_err());
+        assert!(parse_rate("often").is_err());
+    }
+}
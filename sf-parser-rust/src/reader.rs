@@ -0,0 +1,158 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::io::{self, BufRead};
+use tempfile::NamedTempFile;
+
+/// Lines longer than this are cut off unless overridden with `--max-line-length`.
+pub const DEFAULT_MAX_LINE_BYTES: usize = 1024 * 1024;
+
+/// Why a line's text is not a faithful copy of the bytes on disk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DecodeError {
+    /// Invalid UTF-8 sequences were replaced with U+FFFD
+    InvalidUtf8,
+    /// NUL bytes were stripped from the line
+    NulBytes,
+    /// The line exceeded the length cap and was cut off
+    LineTruncated,
+}
+
+/// A line decoded from raw bytes, with a marker if decoding was lossy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodedLine {
+    pub text: String,
+    pub decode_error: Option<DecodeError>,
+}
+
+/// Line iterator that never fails on bad bytes.
+///
+/// Unlike `BufRead::lines`, invalid UTF-8 is decoded lossily, NUL runs left
+/// behind by crashed nodes are dropped, and a line longer than
+/// `max_line_bytes` is truncated without buffering the remainder.
+pub struct LossyLines<R> {
+    reader: R,
+    max_line_bytes: usize,
+    offset: u64,
+    complete_offset: u64,
+    complete_lines: u64,
+}
+
+impl<R: BufRead> LossyLines<R> {
+    pub fn new(reader: R, max_line_bytes: usize) -> Self {
+        LossyLines {
+            reader,
+            max_line_bytes,
+            offset: 0,
+            complete_offset: 0,
+            complete_lines: 0,
+        }
+    }
+
+    /// Bytes consumed from the reader so far.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Byte offset just past the last newline-terminated line. A final line
+    /// without a newline may still be growing, so resumable readers restart
+    /// from here rather than from `offset`.
+    pub fn complete_offset(&self) -> u64 {
+        self.complete_offset
+    }
+
+    /// Number of newline-terminated lines read so far.
+    pub fn complete_lines(&self) -> u64 {
+        self.complete_lines
+    }
+
+    /// Read raw bytes up to the next newline, keeping at most
+    /// `max_line_bytes`. Returns `None` at end of input.
+    fn read_raw_line(&mut self) -> io::Result<Option<(Vec<u8>, bool)>> {
+        let mut buf = Vec::new();
+        let mut truncated = false;
+        let mut read_any = false;
+
+        loop {
+            let available = match self.reader.fill_buf() {
+                Ok(available) => available,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            if available.is_empty() {
+                break;
+            }
+            read_any = true;
+
+            let (chunk, done) = match available.iter().position(|&b| b == b'\n') {
+                Some(pos) => (&available[..pos], Some(pos + 1)),
+                None => (available, None),
+            };
+
+            let room = self.max_line_bytes.saturating_sub(buf.len());
+            if chunk.len() > room {
+                truncated = true;
+            }
+            buf.extend_from_slice(&chunk[..chunk.len().min(room)]);
+
+            let consumed = done.unwrap_or(available.len());
+            self.reader.consume(consumed);
+            self.offset += consumed as u64;
+            if done.is_some() {
+                self.complete_offset = self.offset;
+                self.complete_lines += 1;
+                break;
+            }
+        }
+
+        Ok(read_any.then_some((buf, truncated)))
+    }
+}
+
+impl<R: BufRead> Iterator for LossyLines<R> {
+    type Item = io::Result<DecodedLine>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.read_raw_line() {
+            Ok(Some((bytes, truncated))) => Some(Ok(decode_line(bytes, truncated))),
+            Ok(None) => None,
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+/// Decode one line's bytes. When several problems apply, truncation wins over
+/// invalid UTF-8, which wins over stripped NULs.
+pub fn decode_line(mut bytes: Vec<u8>, truncated: bool) -> DecodedLine {
+    if bytes.last() == Some(&b'\r') {
+        bytes.pop();
+    }
+
+    let mut decode_error = None;
+    if bytes.contains(&0) {
+        bytes.retain(|&b| b != 0);
+        decode_error = Some(DecodeError::NulBytes);
+    }
+
+    let text = match String::from_utf8(bytes) {
+        Ok(text) => text,
+        Err(e) => {
+            decode_error = Some(DecodeError::InvalidUtf8);
+            String::from_utf8_lossy(e.as_bytes()).into_owned()
+        }
+    };
+
+    if truncated {
+        decode_error = Some(DecodeError::LineTruncated);
+    }
+
+    DecodedLine { text, decode_error }
+}
+
+/// Copy stdin to a temporary file. Schema discovery needs two passes over
+/// the input, which a pipe can't provide; the file is deleted on drop.
+pub fn spool_stdin() -> Result<NamedTempFile> {
+    let mut spool = NamedTempFile::new().context("Failed to create spool file for stdin")?;
+    io::copy(&mut io::stdin().lock(), &mut spool).context("Failed to read stdin")?;
+    Ok(spool)
+}
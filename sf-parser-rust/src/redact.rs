@@ -0,0 +1,355 @@
+use aho_corasick::AhoCorasick;
+use anyhow::{Context, Result};
+use hmac::{Hmac, Mac};
+use rayon::prelude::*;
+use regex::Regex;
+use serde::Deserialize;
+use sha2::Sha256;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+
+/// Environment variable holding the pseudonym key when no key file is given.
+pub const REDACT_KEY_ENV: &str = "SF_REDACT_KEY";
+
+/// Hex digits of the keyed hash kept in each pseudonym.
+const TOKEN_HEX_LEN: usize = 12;
+
+/// Built-in rules as (name, pattern). When a pattern has a capture group,
+/// only group 1 is replaced, so `sip=` or `user=` stay readable.
+const DEFAULT_RULES: &[(&str, &str)] = &[
+    ("ip", r"\b(?:\d{1,3}\.){3}\d{1,3}\b"),
+    ("uuid", r"\b[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}\b"),
+    // A full LDAP DN (`cn=x,ou=service accounts,dc=...`) or a plain name
+    ("user", r"\b(?:user|username|userName)=\[?((?:\w+=[^,=\]]+,)*\w+=[^\s,=\]]+|[^\s\]]+)"),
+    // The node name in the log header, plus name fields in the content
+    ("host", r"^\S+\s+(\S+)\s+\S+\[\d+\]:"),
+    ("host", r"\b(?:hostname|hostName|nodeName)=\[?([^\s,\]}]+)"),
+    ("serial", r"\b(?:serial|serialNumber|chassisSerial)=\[?([^\s,\]}]+)"),
+];
+
+/// One redaction rule: matches of `pattern` (or its first capture group)
+/// are replaced with a `<name>-<hash>` pseudonym.
+#[derive(Debug, Clone)]
+pub struct RedactRule {
+    pub name: String,
+    pattern: Regex,
+}
+
+impl RedactRule {
+    pub fn new(name: &str, pattern: &str) -> Result<Self> {
+        Ok(RedactRule {
+            name: name.to_string(),
+            pattern: Regex::new(pattern)
+                .with_context(|| format!("Invalid pattern for redaction rule {}: {}", name, pattern))?,
+        })
+    }
+
+    /// The built-in rules for IPs, UUIDs, usernames, hostnames and serials.
+    pub fn defaults() -> Vec<RedactRule> {
+        DEFAULT_RULES
+            .iter()
+            .map(|(name, pattern)| RedactRule::new(name, pattern).expect("built-in redaction rule"))
+            .collect()
+    }
+
+    /// Load rules from a TOML file:
+    ///
+    /// ```toml
+    /// include_defaults = true     # keep the built-in rules (default)
+    /// disable = ["serial"]        # drop built-in rules by name
+    ///
+    /// [[rule]]
+    /// name = "volume"
+    /// pattern = 'volumeName=(\S+)'
+    /// ```
+    pub fn load(path: &Path) -> Result<Vec<RedactRule>> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read redaction rules: {}", path.display()))?;
+        let file: RulesFile = toml::from_str(&text)
+            .with_context(|| format!("Invalid redaction rules: {}", path.display()))?;
+
+        let mut rules = Vec::new();
+        if file.include_defaults {
+            rules.extend(
+                RedactRule::defaults()
+                    .into_iter()
+                    .filter(|rule| !file.disable.contains(&rule.name)),
+            );
+        }
+        for spec in &file.rule {
+            rules.push(RedactRule::new(&spec.name, &spec.pattern)?);
+        }
+        Ok(rules)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RulesFile {
+    #[serde(default = "default_true")]
+    include_defaults: bool,
+    #[serde(default)]
+    disable: Vec<String>,
+    #[serde(default)]
+    rule: Vec<RuleSpec>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleSpec {
+    name: String,
+    pattern: String,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Rewrites log lines so sensitive values become stable pseudonyms.
+///
+/// Redaction runs on the raw line before parsing, so `raw_line`, `content`,
+/// `hostname` and the dynamic fields all see the same tokens. A pseudonym is
+/// an HMAC-SHA256 of the value under a secret key: the same value maps to
+/// the same token in every field, file and run that uses the same key, and
+/// can't be reversed or brute-forced without it.
+///
+/// Values a rule has found are remembered for the rest of the run (clones
+/// share them), so a hostname matched in one line's header is also replaced
+/// where a later line mentions it in free text with no rule to catch it.
+#[derive(Clone)]
+pub struct Redactor {
+    key: Vec<u8>,
+    rules: Vec<RedactRule>,
+    seen: Arc<RwLock<SeenValues>>,
+}
+
+/// Every value pseudonymized so far, with the rule that first found it.
+///
+/// Lookups go through Aho-Corasick matchers, each over a contiguous run of
+/// `values`. New values get a matcher of their own, merged with the one
+/// before it once it is as large, like a binary counter: a run holding `n`
+/// values scans each line with O(log n) matchers and rebuilds each value
+/// O(log n) times.
+#[derive(Default)]
+struct SeenValues {
+    values: Vec<(String, String)>,
+    index: HashMap<String, usize>,
+    /// (matcher, index of its first value)
+    matchers: Vec<(AhoCorasick, usize)>,
+}
+
+impl SeenValues {
+    fn add<'a>(&mut self, found: impl IntoIterator<Item = (&'a str, &'a str)>) {
+        let start = self.values.len();
+        for (value, name) in found {
+            if !self.index.contains_key(value) {
+                self.index.insert(value.to_string(), self.values.len());
+                self.values.push((value.to_string(), name.to_string()));
+            }
+        }
+        if self.values.len() == start {
+            return;
+        }
+
+        self.matchers.push((self.matcher(start), start));
+        while let [.., (older, _), (newer, _)] = self.matchers.as_slice() {
+            if newer.patterns_len() < older.patterns_len() {
+                break;
+            }
+            self.matchers.pop();
+            let (_, start) = self.matchers.pop().expect("two matchers");
+            self.matchers.push((self.matcher(start), start));
+        }
+    }
+
+    fn matcher(&self, start: usize) -> AhoCorasick {
+        AhoCorasick::new(self.values[start..].iter().map(|(value, _)| value))
+            .expect("seen values are short literals")
+    }
+
+    /// The seen values that occur in `line`, with their rule names.
+    fn in_line<'s>(&'s self, line: &str) -> Vec<(&'s str, &'s str)> {
+        let mut found: Vec<usize> = Vec::new();
+        for (matcher, start) in &self.matchers {
+            for m in matcher.find_overlapping_iter(line) {
+                let i = start + m.pattern().as_usize();
+                if !found.contains(&i) {
+                    found.push(i);
+                }
+            }
+        }
+        found
+            .into_iter()
+            .map(|i| (self.values[i].0.as_str(), self.values[i].1.as_str()))
+            .collect()
+    }
+}
+
+// Hand-written so the key never ends up in debug output
+impl fmt::Debug for Redactor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Redactor")
+            .field("rules", &self.rules)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Redactor {
+    pub fn new(key: &[u8], rules: Vec<RedactRule>) -> Result<Self> {
+        if key.is_empty() {
+            anyhow::bail!("Redaction key is empty");
+        }
+        Ok(Redactor {
+            key: key.to_vec(),
+            rules,
+            seen: Arc::default(),
+        })
+    }
+
+    /// Read the key from `key_file`, or from `$SF_REDACT_KEY` when no file
+    /// is given. Surrounding whitespace in the file is ignored.
+    pub fn load_key(key_file: Option<&Path>) -> Result<Vec<u8>> {
+        let key = match key_file {
+            Some(path) => fs::read_to_string(path)
+                .with_context(|| format!("Failed to read redaction key: {}", path.display()))?
+                .trim()
+                .to_string(),
+            None => std::env::var(REDACT_KEY_ENV).with_context(|| {
+                format!("--redact needs a key: set {} or pass --redact-key-file", REDACT_KEY_ENV)
+            })?,
+        };
+        Ok(key.into_bytes())
+    }
+
+    /// The pseudonym for `value` under rule `name`.
+    pub fn pseudonym(&self, name: &str, value: &str) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.key).expect("HMAC accepts any key length");
+        mac.update(value.as_bytes());
+        let digest = mac.finalize().into_bytes();
+        let hex: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+        format!("{}-{}", name, &hex[..TOKEN_HEX_LEN])
+    }
+
+    /// The values the rules find in `line`, with their rule names.
+    fn find<'a>(&'a self, line: &'a str) -> Vec<(&'a str, &'a str)> {
+        let mut values: Vec<(&str, &str)> = Vec::new();
+        for rule in &self.rules {
+            for captures in rule.pattern.captures_iter(line) {
+                let found = captures.get(1).or_else(|| captures.get(0));
+                if let Some(found) = found.filter(|m| !m.is_empty()) {
+                    if !values.iter().any(|(value, _)| *value == found.as_str()) {
+                        values.push((found.as_str(), &rule.name));
+                    }
+                }
+            }
+        }
+        values
+    }
+
+    /// Remember the sensitive values in a batch of lines before redacting
+    /// them in parallel, so one found late in the batch is still replaced in
+    /// the lines before it.
+    pub fn learn<'a>(&self, lines: impl IntoParallelIterator<Item = &'a str>) {
+        let found: Vec<(&str, &str)> = lines
+            .into_par_iter()
+            .flat_map_iter(|line| self.find(line))
+            .collect();
+        self.seen.write().expect("seen values lock").add(found);
+    }
+
+    /// Replace every sensitive value in `line`. A value found by any rule is
+    /// replaced wherever else it occurs in the line too, so a hostname
+    /// matched in the header is also caught in free text, and so is any
+    /// value found earlier in the run.
+    pub fn redact<'a>(&self, line: &'a str) -> Cow<'a, str> {
+        let found = self.find(line);
+        let seen = self.seen.read().expect("seen values lock");
+        let unseen = found.iter().any(|(value, _)| !seen.index.contains_key(*value));
+        let mut values = found.clone();
+        for (value, name) in seen.in_line(line) {
+            if !values.iter().any(|(v, _)| *v == value) {
+                values.push((value, name));
+            }
+        }
+        if values.is_empty() {
+            return Cow::Borrowed(line);
+        }
+
+        // Claim occurrences longest value first so a value that is part of
+        // a longer one doesn't split it
+        values.sort_by_key(|(value, _)| std::cmp::Reverse(value.len()));
+        let mut spans: Vec<(usize, usize, String)> = Vec::new();
+        for (value, name) in values {
+            let token = self.pseudonym(name, value);
+            for (start, _) in line.match_indices(value) {
+                let end = start + value.len();
+                if spans.iter().all(|(s, e, _)| end <= *s || start >= *e) {
+                    spans.push((start, end, token.clone()));
+                }
+            }
+        }
+        spans.sort_by_key(|(start, _, _)| *start);
+
+        let mut redacted = String::with_capacity(line.len());
+        let mut pos = 0;
+        for (start, end, token) in spans {
+            redacted.push_str(&line[pos..start]);
+            redacted.push_str(&token);
+            pos = end;
+        }
+        redacted.push_str(&line[pos..]);
+
+        drop(seen);
+        if unseen {
+            self.seen.write().expect("seen values lock").add(found);
+        }
+        Cow::Owned(redacted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pseudonyms() {
+        let redactor = Redactor::new(b"team-secret", RedactRule::defaults()).unwrap();
+        let ip = redactor.pseudonym("ip", "169.28.167.167");
+        assert!(ip.starts_with("ip-") && ip.len() == 3 + TOKEN_HEX_LEN);
+        assert_eq!(redactor.pseudonym("ip", "169.28.167.167"), ip);
+        assert_ne!(Redactor::new(b"other-secret", RedactRule::defaults()).unwrap().pseudonym("ip", "169.28.167.167"), ip);
+        assert!(Redactor::new(b"", RedactRule::defaults()).is_err());
+
+        // Every occurrence of a found value is replaced, not just the match
+        let line = "sip=169.28.167.167 peer 169.28.167.167";
+        assert_eq!(redactor.redact(line), format!("sip={} peer {}", ip, ip));
+        assert!(matches!(redactor.redact("nothing to hide"), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_values_seen_earlier() {
+        let redactor = Redactor::new(b"team-secret", RedactRule::defaults()).unwrap();
+        let host = redactor.pseudonym("host", "icpbasi03037");
+        assert_eq!(redactor.redact("hostname=icpbasi03037 up"), format!("hostname={} up", host));
+        // No rule matches a bare name, but it was seen on the line before
+        assert_eq!(redactor.redact("lost contact with icpbasi03037"), format!("lost contact with {}", host));
+        assert!(matches!(redactor.redact("nothing to hide"), Cow::Borrowed(_)));
+
+        // A batch learned up front covers lines before the match too, and
+        // clones share what has been seen
+        let clone = redactor.clone();
+        clone.learn(vec!["mentions node07", "nodeName=node07"]);
+        assert_eq!(redactor.redact("mentions node07"), format!("mentions {}", redactor.pseudonym("host", "node07")));
+
+        // Many values spread over several matchers are all still found
+        for i in 0..100 {
+            redactor.redact(&format!("serial=SN{:04}x", i));
+        }
+        assert_eq!(redactor.redact("saw SN0042x"), format!("saw {}", redactor.pseudonym("serial", "SN0042x")));
+        assert_eq!(redactor.redact("saw SN0099x"), format!("saw {}", redactor.pseudonym("serial", "SN0099x")));
+    }
+}
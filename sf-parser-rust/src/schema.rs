@@ -0,0 +1,73 @@
+use std::collections::HashSet;
+
+use crate::LogRecord;
+
+/// Schema strategy for streaming output, where the whole input can't be
+/// sampled up front. The field set only ever grows, and every growth bumps
+/// the schema version, so a later record's columns are always a superset of
+/// an earlier record's.
+#[derive(Debug, Default)]
+pub struct IncrementalSchema {
+    fields: HashSet<String>,
+    version: u32,
+}
+
+impl IncrementalSchema {
+    /// Start from a known field set, e.g. the result of schema discovery or
+    /// the schema stored in a checkpoint.
+    pub fn with_fields(fields: HashSet<String>, version: u32) -> Self {
+        IncrementalSchema { fields, version }
+    }
+
+    /// Register the record's dynamic fields. Returns how many were new.
+    pub fn observe(&mut self, record: &LogRecord) -> usize {
+        let before = self.fields.len();
+        for key in record.dynamic_fields.keys() {
+            if !self.fields.contains(key) {
+                self.fields.insert(key.clone());
+            }
+        }
+        let added = self.fields.len() - before;
+        if added > 0 {
+            self.version += 1;
+        }
+        added
+    }
+
+    pub fn fields(&self) -> &HashSet<String> {
+        &self.fields
+    }
+
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SolidFireParser;
+
+    #[test]
+    fn test_schema_only_grows() {
+        let parser = SolidFireParser::new().unwrap();
+        let line = |fields: &str| format!("2025-06-05T00:20:07.858372Z icpbasi03037 master-1[112875]: [APP-5] [MS] 2069182 BSDirector ms/ClusterStatistics.cpp:1452:GetBlockDriveUsageFromStats| {}", fields);
+        let first = parser.parse_line(&line("serviceID=230"), 1);
+        let second = parser.parse_line(&line("volumeID=1207"), 2);
+
+        let mut schema = IncrementalSchema::default();
+        assert_eq!(schema.observe(&first), 1);
+        assert_eq!(schema.observe(&second), 1);
+        assert_eq!(schema.observe(&first), 0);
+        assert_eq!(schema.version(), 2);
+        assert!(schema.fields().contains("serviceID") && schema.fields().contains("volumeID"));
+    }
+}
@@ -0,0 +1,92 @@
+use serde::{Deserialize, Serialize};
+
+/// Normalized log severity, ordered from least to most severe.
+///
+/// SolidFire tags application messages as `APP-n`, where `n` is the syslog
+/// priority (0 = emergency ... 7 = debug). Plain syslog-style tags such as
+/// `ERROR` or `WARN` are also accepted. The mapping is:
+///
+/// | level tag                                       | severity    | severity_num |
+/// |-------------------------------------------------|-------------|--------------|
+/// | `APP-7`, `DEBUG`, `TRACE`                       | `debug`     | 0            |
+/// | `APP-6`, `INFO`                                 | `info`      | 1            |
+/// | `APP-5`, `NOTICE`                               | `notice`    | 2            |
+/// | `APP-4`, `WARN`, `WARNING`                      | `warn`      | 3            |
+/// | `APP-3`, `ERR`, `ERROR`                         | `error`     | 4            |
+/// | `APP-2`, `CRIT`, `CRITICAL`                     | `critical`  | 5            |
+/// | `APP-1`, `ALERT`                                | `alert`     | 6            |
+/// | `APP-0`, `EMERG`, `EMERGENCY`, `FATAL`, `PANIC` | `emergency` | 7            |
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Debug,
+    Info,
+    Notice,
+    Warn,
+    Error,
+    Critical,
+    Alert,
+    Emergency,
+}
+
+impl Severity {
+    /// Normalize a raw `level` tag. Returns `None` for tags we don't recognize.
+    pub fn from_level(level: &str) -> Option<Severity> {
+        let level = level.trim();
+        if let Some(priority) = level
+            .strip_prefix("APP-")
+            .or_else(|| level.strip_prefix("app-"))
+        {
+            return priority.parse::<u8>().ok().and_then(Severity::from_syslog_priority);
+        }
+
+        match level.to_ascii_uppercase().as_str() {
+            "DEBUG" | "TRACE" => Some(Severity::Debug),
+            "INFO" => Some(Severity::Info),
+            "NOTICE" => Some(Severity::Notice),
+            "WARN" | "WARNING" => Some(Severity::Warn),
+            "ERR" | "ERROR" => Some(Severity::Error),
+            "CRIT" | "CRITICAL" => Some(Severity::Critical),
+            "ALERT" => Some(Severity::Alert),
+            "EMERG" | "EMERGENCY" | "FATAL" | "PANIC" => Some(Severity::Emergency),
+            _ => None,
+        }
+    }
+
+    /// Map a syslog priority (0 = emergency, 7 = debug) to a severity.
+    pub fn from_syslog_priority(priority: u8) -> Option<Severity> {
+        match priority {
+            0 => Some(Severity::Emergency),
+            1 => Some(Severity::Alert),
+            2 => Some(Severity::Critical),
+            3 => Some(Severity::Error),
+            4 => Some(Severity::Warn),
+            5 => Some(Severity::Notice),
+            6 => Some(Severity::Info),
+            7 => Some(Severity::Debug),
+            _ => None,
+        }
+    }
+
+    /// Numeric rank where larger means more severe (debug = 0, emergency = 7).
+    pub fn as_num(self) -> u8 {
+        self as u8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_level() {
+        assert_eq!(Severity::from_level("APP-5"), Some(Severity::Notice));
+        assert_eq!(Severity::from_level("APP-3"), Some(Severity::Error));
+        assert_eq!(Severity::from_level("ERROR"), Some(Severity::Error));
+        assert_eq!(Severity::from_level("warning"), Some(Severity::Warn));
+        assert_eq!(Severity::from_level("APP-9"), None);
+        assert!(Severity::Warn < Severity::Error);
+        assert_eq!(Severity::Notice.as_num(), 2);
+        assert_eq!(serde_json::to_value(Severity::Warn).unwrap(), serde_json::json!("warn"));
+    }
+}
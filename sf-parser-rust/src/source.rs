@@ -0,0 +1,72 @@
+/// Components of a SolidFire `source` column such as
+/// `ms/ClusterStatistics.cpp:1452:GetBlockDriveUsageFromStats`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SourceLocation {
+    /// Source file including its directory, e.g. `ms/ClusterStatistics.cpp`
+    pub file: Option<String>,
+    pub line: Option<u32>,
+    /// Function name, e.g. `GetBlockDriveUsageFromStats`
+    pub func: Option<String>,
+    /// Top-level directory of the source file (`ms`, `cs`, `httpserver`, ...)
+    pub subsystem: Option<String>,
+}
+
+impl SourceLocation {
+    pub fn parse(source: &str) -> SourceLocation {
+        let source = source.trim();
+        if source.is_empty() {
+            return SourceLocation::default();
+        }
+
+        // file:line:function - the function may itself contain `::`, so only
+        // split off the first two fields. Without a numeric line the rest is
+        // all function (`file:Func`).
+        let (file, rest) = source.split_once(':').unwrap_or((source, ""));
+        let file = Some(file).filter(|s| !s.is_empty());
+        let (line, func) = match rest.split_once(':').unwrap_or((rest, "")) {
+            (line, func) if line.parse::<u32>().is_ok() => (line.parse().ok(), func),
+            _ => (None, rest),
+        };
+        let func = Some(func).filter(|s| !s.is_empty());
+
+        let subsystem = file
+            .and_then(|f| f.split_once('/'))
+            .map(|(dir, _)| dir)
+            .filter(|dir| !dir.is_empty());
+
+        SourceLocation {
+            file: file.map(|s| s.to_string()),
+            line,
+            func: func.map(|s| s.to_string()),
+            subsystem: subsystem.map(|s| s.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let location = SourceLocation::parse("ms/ClusterStatistics.cpp:1452:GetBlockDriveUsageFromStats");
+        assert_eq!(location.file.as_deref(), Some("ms/ClusterStatistics.cpp"));
+        assert_eq!(location.line, Some(1452));
+        assert_eq!(location.func.as_deref(), Some("GetBlockDriveUsageFromStats"));
+        assert_eq!(location.subsystem.as_deref(), Some("ms"));
+
+        let location = SourceLocation::parse("Main.cpp:12:Foo::Bar");
+        assert_eq!(location.func.as_deref(), Some("Foo::Bar"));
+        assert_eq!(location.subsystem, None);
+
+        // No line number: everything after the file is the function
+        let location = SourceLocation::parse("ms/Main.cpp:Foo::Bar");
+        assert_eq!(location.file.as_deref(), Some("ms/Main.cpp"));
+        assert_eq!(location.line, None);
+        assert_eq!(location.func.as_deref(), Some("Foo::Bar"));
+
+        let location = SourceLocation::parse("Main.cpp:12");
+        assert_eq!((location.line, location.func), (Some(12), None));
+        assert_eq!(SourceLocation::parse("  "), SourceLocation::default());
+    }
+}
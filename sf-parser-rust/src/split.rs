@@ -0,0 +1,314 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+use std::io::Write;
+use std::path::Path;
+
+use crate::dataset::ParquetDataset;
+use crate::open_files::{OpenFiles, MAX_OPEN_FILES};
+use crate::output::{AtomicFile, OutputFormat, RecordWriter, StagedDir};
+use crate::summary::TimeSpan;
+use crate::LogRecord;
+
+/// File listing every partition of a split output.
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// Partition name for records that lack the split field.
+const UNKNOWN_PARTITION: &str = "unknown";
+
+/// What `--split-by` partitions records on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SplitBy {
+    Component,
+    Hostname,
+    Hour,
+    Day,
+}
+
+impl SplitBy {
+    pub fn from_name(name: &str) -> Option<SplitBy> {
+        match name.to_ascii_lowercase().as_str() {
+            "component" => Some(SplitBy::Component),
+            "hostname" | "host" => Some(SplitBy::Hostname),
+            "hour" => Some(SplitBy::Hour),
+            "day" | "date" => Some(SplitBy::Day),
+            _ => None,
+        }
+    }
+
+    /// The partition a record belongs to, e.g. `MS`, `2025-06-05` or
+    /// `2025-06-05T09`.
+    pub fn partition(self, record: &LogRecord) -> &str {
+        let value = match self {
+            SplitBy::Component => record.component.as_deref(),
+            SplitBy::Hostname => record.hostname.as_deref(),
+            SplitBy::Hour => (!record.date.is_empty()).then(|| record.timestamp.get(..13)).flatten(),
+            SplitBy::Day => (!record.date.is_empty()).then_some(record.date.as_str()),
+        };
+        value.filter(|v| !v.is_empty()).unwrap_or(UNKNOWN_PARTITION)
+    }
+}
+
+/// One entry of the split manifest.
+#[derive(Debug, Clone, Serialize)]
+pub struct PartitionSummary {
+    pub partition: String,
+    pub file: String,
+    pub records: usize,
+    pub time_span: TimeSpan,
+}
+
+#[derive(Debug, Serialize)]
+struct Manifest<'a> {
+    split_by: SplitBy,
+    format: &'static str,
+    records: usize,
+    partitions: &'a [PartitionSummary],
+}
+
+struct Partition {
+    writer: RecordWriter,
+    file: String,
+    time_span: TimeSpan,
+}
+
+/// Writes each record to a file per partition inside one directory, so a
+/// single parse pass replaces a filter run per component, host or hour.
+/// Files are opened as partitions first appear, at most `MAX_OPEN_FILES`
+/// at a time: the least recently written is closed to make room and
+/// reopened in append mode when its partition comes round again. `finish`
+/// closes them all, writes `manifest.json` alongside and moves the directory
+/// into place, replacing an earlier split only with `overwrite`, like
+/// `ParquetDataset`.
+pub struct SplitWriter {
+    staging: StagedDir,
+    split_by: SplitBy,
+    format: OutputFormat,
+    partitions: BTreeMap<String, Partition>,
+    open_files: OpenFiles<String>,
+    file_names: HashSet<String>,
+    records: usize,
+}
+
+impl SplitWriter {
+    pub fn create(dir: &Path, split_by: SplitBy, format: OutputFormat, overwrite: bool) -> Result<Self> {
+        let staging = StagedDir::create(dir, overwrite, "split output", |entry| {
+            entry == MANIFEST_FILE || entry.ends_with(".json") || entry.ends_with(".ndjson")
+        })?;
+        Ok(SplitWriter {
+            staging,
+            split_by,
+            format,
+            partitions: BTreeMap::new(),
+            open_files: OpenFiles::new(MAX_OPEN_FILES),
+            file_names: HashSet::new(),
+            records: 0,
+        })
+    }
+
+    pub fn write_record(&mut self, record: &LogRecord) -> Result<()> {
+        let name = self.split_by.partition(record);
+        if let Some(idle) = self.open_files.touch(name) {
+            self.partitions.get_mut(&idle).expect("open file has a partition").writer.suspend()?;
+        }
+        if !self.partitions.contains_key(name) {
+            let file = self.file_name_for(name);
+            let writer = RecordWriter::create(&self.staging.path().join(&file), self.format)?;
+            self.partitions.insert(name.to_string(), Partition {
+                writer,
+                file,
+                time_span: TimeSpan::default(),
+            });
+        }
+
+        let partition = self.partitions.get_mut(name).expect("partition was just created");
+        partition.writer.resume()?;
+        partition.writer.write_record(record)?;
+        partition.time_span.observe(&record.timestamp);
+        self.records += 1;
+        Ok(())
+    }
+
+    pub fn records(&self) -> usize {
+        self.records
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        for partition in self.partitions.values_mut() {
+            partition.writer.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Close every partition file, write the manifest and move the
+    /// directory into place.
+    pub fn finish(self) -> Result<Vec<PartitionSummary>> {
+        let mut summaries = Vec::with_capacity(self.partitions.len());
+        for (name, partition) in self.partitions {
+            summaries.push(PartitionSummary {
+                partition: name,
+                file: partition.file,
+                records: partition.writer.records(),
+                time_span: partition.time_span,
+            });
+            partition.writer.finish()?;
+        }
+
+        let manifest = Manifest {
+            split_by: self.split_by,
+            format: self.format.extension(),
+            records: self.records,
+            partitions: &summaries,
+        };
+        let mut file = AtomicFile::create(&self.staging.path().join(MANIFEST_FILE))?;
+        file.write_all(&serde_json::to_vec_pretty(&manifest)?)?;
+        file.commit()?;
+        self.staging.commit()?;
+        Ok(summaries)
+    }
+
+    /// A file name for `partition` that is safe on any filesystem and not
+    /// already taken by another partition that sanitized to the same name.
+    fn file_name_for(&mut self, partition: &str) -> String {
+        let stem: String = partition
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') { c } else { '_' })
+            .collect();
+        let extension = self.format.extension();
+
+        let mut file = format!("{}.{}", stem, extension);
+        let mut n = 2;
+        while !self.file_names.insert(file.clone()) || file == MANIFEST_FILE {
+            file = format!("{}-{}.{}", stem, n, extension);
+            n += 1;
+        }
+        file
+    }
+}
+
+/// Where parsed records go: one output, one per partition, or a Parquet
+/// dataset.
+pub enum RecordSink {
+    Single(RecordWriter),
+    Split(SplitWriter),
+    Dataset(ParquetDataset),
+}
+
+impl RecordSink {
+    pub fn write_record(&mut self, record: &LogRecord) -> Result<()> {
+        match self {
+            RecordSink::Single(writer) => writer.write_record(record),
+            RecordSink::Split(writer) => writer.write_record(record),
+            RecordSink::Dataset(writer) => writer.write_record(record),
+        }
+    }
+
+    pub fn records(&self) -> usize {
+        match self {
+            RecordSink::Single(writer) => writer.records(),
+            RecordSink::Split(writer) => writer.records(),
+            RecordSink::Dataset(writer) => writer.records(),
+        }
+    }
+
+    /// Bytes written to a single output; partitioned output is never
+    /// checkpointed.
+    pub fn bytes_written(&self) -> u64 {
+        match self {
+            RecordSink::Single(writer) => writer.bytes_written(),
+            RecordSink::Split(_) | RecordSink::Dataset(_) => 0,
+        }
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        match self {
+            RecordSink::Single(writer) => writer.flush(),
+            RecordSink::Split(writer) => writer.flush(),
+            RecordSink::Dataset(writer) => writer.flush(),
+        }
+    }
+
+    pub fn finish(self) -> Result<()> {
+        match self {
+            RecordSink::Single(writer) => writer.finish(),
+            RecordSink::Split(writer) => writer.finish().map(|_| ()),
+            RecordSink::Dataset(writer) => writer.finish().map(|_| ()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SolidFireParser;
+    use std::fs;
+
+    #[test]
+    fn test_partition() {
+        let parser = SolidFireParser::new().unwrap();
+        let record = parser.parse_line("2025-06-12T08:35:00.177183Z icpbasi03037 master-1[112875]: [APP-5] [MS] 2069183 Scheduler a.cpp:1:F| x=1", 1);
+        assert_eq!(SplitBy::Component.partition(&record), "MS");
+        assert_eq!(SplitBy::Hour.partition(&record), "2025-06-12T08");
+        assert_eq!(SplitBy::Day.partition(&record), "2025-06-12");
+        assert_eq!(SplitBy::Hostname.partition(&record), "icpbasi03037");
+        assert_eq!(SplitBy::Hour.partition(&parser.parse_line("garbage", 2)), UNKNOWN_PARTITION);
+        assert_eq!(SplitBy::from_name("host"), Some(SplitBy::Hostname));
+        assert_eq!(SplitBy::from_name("week"), None);
+    }
+
+    #[test]
+    fn test_open_file_limit() {
+        let parser = SolidFireParser::new().unwrap();
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let line = |component: &str, n: u32| parser.parse_line(&format!(
+            "2025-06-12T08:35:00.177183Z icpbasi03037 master-1[112875]: [APP-5] [{}] 2069183 Scheduler a.cpp:1:F| n={}", component, n), n);
+
+        for format in [OutputFormat::Json, OutputFormat::Ndjson] {
+            let dir = temp_dir.path().join(format.extension());
+            let mut writer = SplitWriter::create(&dir, SplitBy::Component, format, false).unwrap();
+            writer.open_files = OpenFiles::new(2);
+            // Round-robin over more partitions than may be open at once
+            for n in 0..12 {
+                writer.write_record(&line(["MS", "Vvols", "Snaps", "Api"][n as usize % 4], n)).unwrap();
+            }
+            writer.flush().unwrap();
+            let summaries = writer.finish().unwrap();
+            assert_eq!(summaries.len(), 4);
+
+            let text = fs::read_to_string(dir.join(format!("MS.{}", format.extension()))).unwrap();
+            let records: Vec<serde_json::Value> = match format {
+                OutputFormat::Json => serde_json::from_str(&text).unwrap(),
+                _ => text.lines().map(|line| serde_json::from_str(line).unwrap()).collect(),
+            };
+            let numbers: Vec<_> = records.iter().map(|r| r["n"].as_u64().unwrap()).collect();
+            assert_eq!(numbers, [0, 4, 8]);
+        }
+    }
+
+    #[test]
+    fn test_replace_existing() {
+        let parser = SolidFireParser::new().unwrap();
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let dir = temp_dir.path().join("split");
+        let write = |component: &str, overwrite: bool| -> Result<Vec<PartitionSummary>> {
+            let mut writer = SplitWriter::create(&dir, SplitBy::Component, OutputFormat::Ndjson, overwrite)?;
+            writer.write_record(&parser.parse_line(&format!(
+                "2025-06-12T08:35:00.177183Z icpbasi03037 master-1[112875]: [APP-5] [{}] 2069183 Scheduler a.cpp:1:F| n=1", component), 1))?;
+            writer.finish()
+        };
+
+        write("MS", false).unwrap();
+        assert!(write("Api", false).is_err());
+        write("Api", true).unwrap();
+        let mut files: Vec<String> = fs::read_dir(&dir).unwrap().map(|e| e.unwrap().file_name().to_string_lossy().into_owned()).collect();
+        files.sort();
+        assert_eq!(files, ["Api.ndjson", MANIFEST_FILE]);
+
+        // Anything that isn't split output is never replaced
+        fs::write(dir.join("notes.txt"), "keep me").unwrap();
+        let error = write("MS", true).err().unwrap().to_string();
+        assert!(error.contains("notes.txt is not part of a split output"), "{}", error);
+        assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 1, "staging should be removed");
+    }
+}
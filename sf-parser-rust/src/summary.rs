@@ -0,0 +1,169 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::ParseStats;
+
+/// Every line parsed and the quality gate (if any) passed.
+pub const EXIT_OK: i32 = 0;
+/// Nothing usable was produced: bad input, I/O error, invalid checkpoint.
+pub const EXIT_FAILURE: i32 = 1;
+/// Output is complete, but `--max-error-rate` was exceeded.
+pub const EXIT_ERROR_RATE: i32 = 3;
+/// Stopped by Ctrl-C; output is partial (kept, checkpointed, or discarded).
+pub const EXIT_INTERRUPTED: i32 = 4;
+
+/// How a run ended. Each status maps to one exit code so scripts can tell
+/// partial success apart from failure without reading the summary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunStatus {
+    Ok,
+    ErrorRateExceeded,
+    Interrupted,
+    Failed,
+}
+
+impl RunStatus {
+    pub fn exit_code(self) -> i32 {
+        match self {
+            RunStatus::Ok => EXIT_OK,
+            RunStatus::ErrorRateExceeded => EXIT_ERROR_RATE,
+            RunStatus::Interrupted => EXIT_INTERRUPTED,
+            RunStatus::Failed => EXIT_FAILURE,
+        }
+    }
+}
+
+/// Earliest and latest record timestamps seen in a run.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct TimeSpan {
+    pub first: Option<String>,
+    pub last: Option<String>,
+}
+
+impl TimeSpan {
+    /// Widen the span to include `timestamp`. Timestamps are fixed-width
+    /// ISO-8601 UTC, so string order is time order; empty ones are skipped.
+    pub fn observe(&mut self, timestamp: &str) {
+        if timestamp.is_empty() {
+            return;
+        }
+        if self.first.as_deref().is_none_or(|first| timestamp < first) {
+            self.first = Some(timestamp.to_string());
+        }
+        if self.last.as_deref().is_none_or(|last| timestamp > last) {
+            self.last = Some(timestamp.to_string());
+        }
+    }
+}
+
+/// Identifies one input the summary describes.
+#[derive(Debug, Clone, Serialize)]
+pub struct InputSummary {
+    pub path: String,
+    pub bytes: Option<u64>,
+    /// SHA-256 of the first `fingerprint_len` bytes, as in checkpoints
+    pub fingerprint: Option<String>,
+    pub fingerprint_len: Option<u64>,
+}
+
+/// The document written by `--summary-json`.
+#[derive(Debug, Clone, Serialize)]
+pub struct RunSummary {
+    pub tool: &'static str,
+    pub version: &'static str,
+    pub status: RunStatus,
+    pub exit_code: i32,
+    pub error: Option<String>,
+    pub inputs: Vec<InputSummary>,
+    pub output: String,
+    pub lines: usize,
+    pub parse_paths: BTreeMap<&'static str, usize>,
+    pub parse_failures: usize,
+    pub error_rate: f64,
+    pub decode_errors: usize,
+    pub rejected_lines: usize,
+    pub duplicates_dropped: usize,
+    pub templates: usize,
+    pub dynamic_fields: usize,
+    /// Dynamic fields renamed to `sf_<key>` to keep clear of fixed columns
+    pub renamed_fields: Vec<String>,
+    pub time_span: TimeSpan,
+    pub duration_secs: f64,
+    pub lines_per_sec: f64,
+}
+
+impl RunSummary {
+    pub fn new(input_paths: &[PathBuf], output_path: &Path, status: RunStatus) -> Self {
+        RunSummary {
+            tool: "sf-parser",
+            version: env!("CARGO_PKG_VERSION"),
+            status,
+            exit_code: status.exit_code(),
+            error: None,
+            inputs: input_paths
+                .iter()
+                .map(|path| InputSummary {
+                    path: path.display().to_string(),
+                    bytes: None,
+                    fingerprint: None,
+                    fingerprint_len: None,
+                })
+                .collect(),
+            output: output_path.display().to_string(),
+            lines: 0,
+            parse_paths: BTreeMap::new(),
+            parse_failures: 0,
+            error_rate: 0.0,
+            decode_errors: 0,
+            rejected_lines: 0,
+            duplicates_dropped: 0,
+            templates: 0,
+            dynamic_fields: 0,
+            renamed_fields: Vec::new(),
+            time_span: TimeSpan::default(),
+            duration_secs: 0.0,
+            lines_per_sec: 0.0,
+        }
+    }
+
+    /// Summary of a run that produced statistics, complete or not.
+    pub fn from_stats(output_path: &Path, status: RunStatus, stats: &ParseStats) -> Self {
+        let duration_secs = stats.duration.as_secs_f64();
+        let mut summary = RunSummary::new(&[], output_path, status);
+        summary.inputs = stats.inputs.clone();
+        summary.lines = stats.total_lines;
+        summary.parse_paths = stats
+            .format_counts
+            .iter()
+            .map(|(format, count)| (format.as_str(), count))
+            .collect();
+        summary.parse_failures = stats.format_counts.failed();
+        summary.error_rate = stats.format_counts.error_rate();
+        summary.decode_errors = stats.decode_errors;
+        summary.rejected_lines = stats.rejected_lines;
+        summary.duplicates_dropped = stats.duplicates_dropped;
+        summary.templates = stats.template_count;
+        summary.dynamic_fields = stats.dynamic_field_count;
+        summary.renamed_fields = stats.renamed_fields.clone();
+        summary.time_span = stats.time_span.clone();
+        summary.duration_secs = duration_secs;
+        if duration_secs > 0.0 {
+            summary.lines_per_sec = stats.total_lines as f64 / duration_secs;
+        }
+        summary
+    }
+
+    pub fn with_error(mut self, error: impl ToString) -> Self {
+        self.error = Some(error.to_string());
+        self
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_vec_pretty(self)?)
+            .with_context(|| format!("Failed to write summary: {}", path.display()))
+    }
+}
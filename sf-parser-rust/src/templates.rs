@@ -0,0 +1,209 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::Path;
+
+use crate::output::AtomicFile;
+
+/// Placeholder for the variable parts of a template.
+pub const WILDCARD: &str = "<*>";
+
+/// Minimum fraction of matching tokens for a message to join a template.
+const SIMILARITY_THRESHOLD: f64 = 0.4;
+
+/// Leading tokens used to route a message through the prefix tree.
+const PREFIX_DEPTH: usize = 2;
+
+/// Children per tree node before new tokens are routed to the wildcard.
+const MAX_CHILDREN: usize = 100;
+
+/// A message template and how often it was seen.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Template {
+    pub template_id: u32,
+    pub template: String,
+    pub count: usize,
+    pub first_seen: String,
+    pub last_seen: String,
+}
+
+#[derive(Debug, Default)]
+struct Node {
+    children: HashMap<String, Node>,
+    clusters: Vec<usize>,
+}
+
+#[derive(Debug)]
+struct Cluster {
+    tokens: Vec<String>,
+    count: usize,
+    first_seen: String,
+    last_seen: String,
+}
+
+/// Online template miner after Drain (He et al., ICWS 2017).
+///
+/// Messages are tokenized on whitespace, and numeric values are masked up
+/// front (`serviceID=230` becomes `serviceID=<*>`). Each message is routed
+/// by token count and its first tokens to a small set of candidate
+/// templates. It joins the most similar one, turning positions that differ
+/// into `<*>`, or starts a new template. Mining is sequential, but each
+/// message costs only a short tree walk and a few comparisons.
+///
+/// Template ids are assigned in order of first appearance and never change.
+/// A template only ever gets more general, so the text a record is tagged
+/// with may be more specific than the final text in the catalog.
+#[derive(Debug, Default)]
+pub struct TemplateMiner {
+    root: HashMap<usize, Node>,
+    clusters: Vec<Cluster>,
+}
+
+impl TemplateMiner {
+    /// Assign `content` to a template. Returns the template id and its
+    /// text as of this message.
+    pub fn observe(&mut self, content: &str, timestamp: &str) -> (u32, String) {
+        let tokens: Vec<String> = content.split_whitespace().map(mask_token).collect();
+
+        let mut node = self.root.entry(tokens.len()).or_default();
+        for token in tokens.iter().take(PREFIX_DEPTH) {
+            let key = if has_digit(token) { WILDCARD } else { token.as_str() };
+            let key = if node.children.contains_key(key) || node.children.len() < MAX_CHILDREN {
+                key
+            } else {
+                WILDCARD
+            };
+            node = node.children.entry(key.to_string()).or_default();
+        }
+
+        let best = node
+            .clusters
+            .iter()
+            .map(|&index| (index, similarity(&self.clusters[index].tokens, &tokens)))
+            .filter(|&(_, score)| score >= SIMILARITY_THRESHOLD)
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+
+        let index = match best {
+            Some((index, _)) => {
+                let cluster = &mut self.clusters[index];
+                for (template_token, token) in cluster.tokens.iter_mut().zip(&tokens) {
+                    if template_token != token {
+                        *template_token = WILDCARD.to_string();
+                    }
+                }
+                cluster.count += 1;
+                if !timestamp.is_empty() {
+                    if cluster.first_seen.is_empty() || timestamp < cluster.first_seen.as_str() {
+                        cluster.first_seen = timestamp.to_string();
+                    }
+                    if timestamp > cluster.last_seen.as_str() {
+                        cluster.last_seen = timestamp.to_string();
+                    }
+                }
+                index
+            }
+            None => {
+                self.clusters.push(Cluster {
+                    tokens,
+                    count: 1,
+                    first_seen: timestamp.to_string(),
+                    last_seen: timestamp.to_string(),
+                });
+                let index = self.clusters.len() - 1;
+                node.clusters.push(index);
+                index
+            }
+        };
+
+        (index as u32 + 1, self.clusters[index].tokens.join(" "))
+    }
+
+    pub fn len(&self) -> usize {
+        self.clusters.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.clusters.is_empty()
+    }
+
+    /// Every template, most frequent first.
+    pub fn catalog(&self) -> Vec<Template> {
+        let mut templates: Vec<Template> = self
+            .clusters
+            .iter()
+            .enumerate()
+            .map(|(index, cluster)| Template {
+                template_id: index as u32 + 1,
+                template: cluster.tokens.join(" "),
+                count: cluster.count,
+                first_seen: cluster.first_seen.clone(),
+                last_seen: cluster.last_seen.clone(),
+            })
+            .collect();
+        templates.sort_by(|a, b| b.count.cmp(&a.count).then(a.template_id.cmp(&b.template_id)));
+        templates
+    }
+
+    /// Write the catalog as a JSON array, replacing any previous catalog
+    /// only once the new one is complete.
+    pub fn write_catalog(&self, path: &Path) -> Result<()> {
+        let mut file = AtomicFile::create(path)?;
+        file.write_all(&serde_json::to_vec_pretty(&self.catalog())?)
+            .with_context(|| format!("Failed to write template catalog: {}", path.display()))?;
+        file.commit()
+    }
+}
+
+/// Fraction of positions where the template has exactly this token.
+fn similarity(template: &[String], tokens: &[String]) -> f64 {
+    if tokens.is_empty() {
+        return 1.0;
+    }
+    let same = template.iter().zip(tokens).filter(|(a, b)| a == b).count();
+    same as f64 / tokens.len() as f64
+}
+
+fn has_digit(token: &str) -> bool {
+    token.bytes().any(|b| b.is_ascii_digit())
+}
+
+/// Mask the variable part of a token: the value of a `key=value` pair, or
+/// the whole token, when it contains a digit.
+fn mask_token(token: &str) -> String {
+    match token.split_once('=') {
+        Some((key, value)) if !key.is_empty() && has_digit(value) => format!("{}={}", key, WILDCARD),
+        Some(_) => token.to_string(),
+        None if has_digit(token) => WILDCARD.to_string(),
+        None => token.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_observe_and_catalog() {
+        let mut miner = TemplateMiner::default();
+
+        let (first, _) = miner.observe("Deleting empty group snapshot groupSnapshotID=13933489 volumeID=1207", "2025-06-12T08:35:00.178356Z");
+        let (second, template) = miner.observe("Deleting empty group snapshot groupSnapshotID=13933490 volumeID=1136", "2025-06-12T08:36:00.000000Z");
+        assert_eq!(first, second);
+        assert_eq!(template, "Deleting empty group snapshot groupSnapshotID=<*> volumeID=<*>");
+
+        // Differing words become wildcards once the message is similar enough
+        let (api, _) = miner.observe("Calling RestAPI::ListVolumes user=admin", "2025-06-12T08:30:00.000000Z");
+        let (api_again, template) = miner.observe("Calling RestAPI::ListVolumes user=vasa", "2025-06-12T08:31:00.000000Z");
+        assert_eq!(api, api_again);
+        assert_eq!(template, "Calling RestAPI::ListVolumes <*>");
+        assert_ne!(miner.observe("Slice snapshot deleted sliceID=1207", "").0, first);
+
+        let catalog = miner.catalog();
+        assert_eq!(catalog.len(), 3);
+        assert_eq!(catalog[0].template_id, first);
+        assert_eq!(catalog[0].count, 2);
+        assert_eq!(catalog[1].first_seen, "2025-06-12T08:30:00.000000Z");
+        assert_eq!(catalog[1].last_seen, "2025-06-12T08:31:00.000000Z");
+    }
+}